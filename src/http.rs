@@ -5,7 +5,21 @@
 // https://developer.mozilla.org/en-US/docs/Web/HTTP/Methods/POST
 // https://developer.mozilla.org/en-US/docs/Web/HTTP/Messages
 
+pub mod chunked;
+pub mod content_disposition;
+pub mod content_transfer_encoding;
+pub mod date;
+pub mod header_map;
 pub mod http;
 pub mod http_message;
+pub mod media_type;
+pub mod message_ref;
+pub mod multipart;
+pub mod parser;
 pub mod request;
 pub mod response;
+pub mod status;
+pub mod typed_header;
+pub mod uri;
+pub mod urlencoded;
+pub mod version;
@@ -0,0 +1,486 @@
+use crate::Result::{self, Err, Ok};
+use std::io::{self, Write};
+use std::str;
+
+use super::header_map::HeaderMap;
+use super::http::{io_result, Header, HeaderCase, ParseHttpError};
+use super::media_type::{is_tchar, is_token};
+
+/// A single chunk of a chunked body with its chunk extensions
+/// (RFC 9112 section 7.1.1).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk {
+    pub extensions: Vec<(String, Option<String>)>,
+    pub data: Vec<u8>,
+}
+impl Chunk {
+    pub fn new(data: Vec<u8>) -> Chunk {
+        Chunk {
+            extensions: Vec::new(),
+            data,
+        }
+    }
+
+    pub fn add_extension(&mut self, name: String, value: Option<String>) -> &mut Chunk {
+        self.extensions.push((name, value));
+        self
+    }
+}
+
+pub struct ChunkedBody {
+    pub chunks: Vec<Chunk>,
+    pub trailers: HeaderMap,
+}
+impl Clone for ChunkedBody {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            trailers: self.trailers.clone(),
+        }
+    }
+}
+impl ChunkedBody {
+    /// Writes every non-empty chunk, then the last chunk and trailers.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        for chunk in &self.chunks {
+            // A zero sized chunk would terminate the body early
+            if chunk.data.is_empty() {
+                continue;
+            }
+            write_chunk(w, chunk)?;
+        }
+        write_last_chunk(w, &self.trailers)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, ParseHttpError> {
+        let mut result = Vec::new();
+        io_result(self.write_to(&mut result))?;
+        Ok(result)
+    }
+
+    pub fn new() -> ChunkedBody {
+        ChunkedBody {
+            chunks: Vec::new(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    /// Splits `data` into chunks of at most `chunk_size` bytes.
+    pub fn from_data(data: &[u8], chunk_size: usize) -> ChunkedBody {
+        let mut result = ChunkedBody::new();
+        for chunk in data.chunks(chunk_size.max(1)) {
+            result.chunks.push(Chunk::new(chunk.to_vec()));
+        }
+        result
+    }
+
+    /// Decodes a complete chunked body from the start of `data`, returning it
+    /// together with the number of bytes it occupied.
+    pub fn parse(data: &[u8]) -> Result<(ChunkedBody, usize), ParseHttpError> {
+        let mut decoder = ChunkedDecoder::new();
+        match decoder.decode(data)? {
+            Some(length) => Ok((decoder.into_body(), length)),
+            None => Err(ParseHttpError::IncompleteMessage),
+        }
+    }
+
+    pub fn add_chunk(&mut self, chunk: Chunk) -> &mut ChunkedBody {
+        self.chunks.push(chunk);
+        self
+    }
+
+    pub fn add_trailer(&mut self, header: Header) -> &mut ChunkedBody {
+        self.trailers.append(header.key, header.value);
+        self
+    }
+
+    /// The payload with the chunked framing removed.
+    pub fn data(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.chunks.iter().map(|c| c.data.len()).sum());
+        for chunk in &self.chunks {
+            result.extend_from_slice(&chunk.data);
+        }
+        result
+    }
+}
+impl Default for ChunkedBody {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes one chunk, for callers that stream a body chunk by chunk.
+pub fn encode_chunk(chunk: &Chunk) -> Result<Vec<u8>, ParseHttpError> {
+    let mut result = Vec::with_capacity(chunk.data.len() + 16);
+    io_result(write_chunk(&mut result, chunk))?;
+    Ok(result)
+}
+
+/// Encodes the last chunk and the trailer section that ends a chunked body.
+pub fn encode_last_chunk(trailers: &HeaderMap) -> Result<Vec<u8>, ParseHttpError> {
+    let mut result = Vec::new();
+    io_result(write_last_chunk(&mut result, trailers))?;
+    Ok(result)
+}
+
+/// Writes one chunk, as `encode_chunk` does.
+pub fn write_chunk(w: &mut impl Write, chunk: &Chunk) -> io::Result<()> {
+    check_extensions(&chunk.extensions)?;
+    write!(w, "{:X}", chunk.data.len())?;
+    write_extensions(w, &chunk.extensions)?;
+    w.write_all(b"\r\n")?;
+    w.write_all(&chunk.data)?;
+    w.write_all(b"\r\n")
+}
+
+/// Writes the last chunk and trailer section, as `encode_last_chunk` does.
+pub fn write_last_chunk(w: &mut impl Write, trailers: &HeaderMap) -> io::Result<()> {
+    w.write_all(b"0\r\n")?;
+    trailers.write_to(w, HeaderCase::Original)?;
+    w.write_all(b"\r\n")
+}
+
+/// Checked before anything is written: a name that is not a token, or a
+/// control character in a value, would end the chunk line early.
+fn check_extensions(extensions: &[(String, Option<String>)]) -> io::Result<()> {
+    for (name, value) in extensions {
+        let valid_name = is_token(name);
+        let valid_value = value.as_ref().is_none_or(|value| {
+            value.bytes().all(|byte| byte == b'\t' || (byte >= b' ' && byte != 0x7f))
+        });
+        if !valid_name || !valid_value {
+            return std::result::Result::Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid chunk extension: {:?}", name),
+            ));
+        }
+    }
+    std::result::Result::Ok(())
+}
+
+fn write_extensions(w: &mut impl Write, extensions: &[(String, Option<String>)]) -> io::Result<()> {
+    for (name, value) in extensions {
+        w.write_all(b";")?;
+        w.write_all(name.as_bytes())?;
+        if let Some(value) = value {
+            w.write_all(b"=")?;
+            if is_token(value) {
+                w.write_all(value.as_bytes())?;
+            } else {
+                w.write_all(b"\"")?;
+                for piece in value.as_bytes().split_inclusive(|&byte| byte == b'"' || byte == b'\\') {
+                    match piece.split_last() {
+                        Some((&last, rest)) if last == b'"' || last == b'\\' => {
+                            w.write_all(rest)?;
+                            w.write_all(&[b'\\', last])?;
+                        }
+                        _ => w.write_all(piece)?,
+                    }
+                }
+                w.write_all(b"\"")?;
+            }
+        }
+    }
+    std::result::Result::Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DecoderState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+/// Incremental chunked decoder. It is fed the whole body received so far,
+/// starting at the first chunk, and resumes where the previous call stopped.
+#[derive(Clone)]
+pub struct ChunkedDecoder {
+    state: DecoderState,
+    position: usize,
+    body: ChunkedBody,
+    data_size: usize,
+    max_data_size: Option<usize>,
+}
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ChunkedDecoder {
+    pub fn new() -> ChunkedDecoder {
+        ChunkedDecoder {
+            state: DecoderState::Size,
+            position: 0,
+            body: ChunkedBody::new(),
+            data_size: 0,
+            max_data_size: None,
+        }
+    }
+
+    /// Fails with `BodyTooLarge` as soon as the announced chunk sizes add up
+    /// to more than `max_data_size`, before the chunk data is buffered.
+    pub fn with_max_data_size(max_data_size: Option<usize>) -> ChunkedDecoder {
+        ChunkedDecoder {
+            max_data_size,
+            ..ChunkedDecoder::new()
+        }
+    }
+
+    /// Returns the length of the encoded body once the trailer section has
+    /// been read, or `None` if more data is needed.
+    pub fn decode(&mut self, buffer: &[u8]) -> Result<Option<usize>, ParseHttpError> {
+        loop {
+            match self.state {
+                DecoderState::Size => {
+                    let Some(line) = self.next_line(buffer) else {
+                        return Ok(None);
+                    };
+                    let (size, extensions) = parse_chunk_line(line)?;
+                    self.data_size = self.data_size.saturating_add(size);
+                    if let Some(max_data_size) = self.max_data_size {
+                        if self.data_size > max_data_size {
+                            return Err(ParseHttpError::BodyTooLarge(max_data_size));
+                        }
+                    }
+                    self.body.chunks.push(Chunk {
+                        extensions,
+                        data: Vec::new(),
+                    });
+                    self.state = if size == 0 {
+                        DecoderState::Trailers
+                    } else {
+                        DecoderState::Data(size)
+                    };
+                }
+                DecoderState::Data(size) => {
+                    if buffer.len() - self.position < size {
+                        return Ok(None);
+                    }
+                    if let Some(chunk) = self.body.chunks.last_mut() {
+                        chunk
+                            .data
+                            .extend_from_slice(&buffer[self.position..self.position + size]);
+                    }
+                    self.position += size;
+                    self.state = DecoderState::DataEnd;
+                }
+                DecoderState::DataEnd => {
+                    if buffer.len() - self.position < 2 {
+                        return Ok(None);
+                    }
+                    if &buffer[self.position..self.position + 2] != b"\r\n" {
+                        return Err(ParseHttpError::ParseBodyError(
+                            "Missing CRLF after chunk data".to_string(),
+                        ));
+                    }
+                    self.position += 2;
+                    self.state = DecoderState::Size;
+                }
+                DecoderState::Trailers => {
+                    let Some(line) = self.next_line(buffer) else {
+                        return Ok(None);
+                    };
+                    if line.is_empty() {
+                        // The last chunk only marks the end, it carries no data
+                        self.body.chunks.pop();
+                        self.state = DecoderState::Done;
+                        continue;
+                    }
+                    let header = Into::<Result<Header, ParseHttpError>>::into(line)?;
+                    self.body.trailers.append(header.key, header.value);
+                }
+                DecoderState::Done => return Ok(Some(self.position)),
+            }
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == DecoderState::Done
+    }
+
+    pub fn into_body(self) -> ChunkedBody {
+        self.body
+    }
+
+    fn next_line<'a>(&mut self, buffer: &'a [u8]) -> Option<&'a [u8]> {
+        let line_end = buffer[self.position..]
+            .windows(2)
+            .position(|window| window == b"\r\n")?;
+        let line = &buffer[self.position..self.position + line_end];
+        self.position += line_end + 2;
+        Some(line)
+    }
+}
+
+type ChunkLine = (usize, Vec<(String, Option<String>)>);
+
+pub(crate) fn parse_chunk_line(line: &[u8]) -> Result<ChunkLine, ParseHttpError> {
+    let line = match str::from_utf8(line) {
+        std::result::Result::Ok(line) => line,
+        std::result::Result::Err(_) => {
+            return Err(ParseHttpError::ParseBodyError(
+                "Invalid utf-8 in chunk line".to_string(),
+            ))
+        }
+    };
+    let (size, mut rest) = match line.find(';') {
+        Some(index) => (&line[..index], &line[index..]),
+        None => (line, ""),
+    };
+    let size = size.trim_end_matches([' ', '\t']);
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseHttpError::ParseBodyError(format!(
+            "Invalid chunk size: {}",
+            size
+        )));
+    }
+    // No buffer can hold more than isize::MAX bytes of chunk data
+    let size = match usize::from_str_radix(size, 16) {
+        std::result::Result::Ok(size) if size <= isize::MAX as usize => size,
+        _ => {
+            return Err(ParseHttpError::ParseBodyError(format!(
+                "Chunk size too large: {}",
+                size
+            )))
+        }
+    };
+
+    let mut extensions = Vec::new();
+    while let Some(stripped) = rest.trim_start_matches([' ', '\t']).strip_prefix(';') {
+        let stripped = stripped.trim_start_matches([' ', '\t']);
+        let name_end = stripped
+            .bytes()
+            .position(|b| !is_tchar(b))
+            .unwrap_or(stripped.len());
+        if name_end == 0 {
+            return Err(ParseHttpError::ParseBodyError(
+                "Invalid chunk extension".to_string(),
+            ));
+        }
+        let name = stripped[..name_end].to_string();
+        rest = stripped[name_end..].trim_start_matches([' ', '\t']);
+
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let (value, remaining) = parse_extension_value(value.trim_start_matches([' ', '\t']))?;
+                rest = remaining;
+                Some(value)
+            }
+            None => None,
+        };
+        extensions.push((name, value));
+    }
+    if !rest.trim().is_empty() {
+        return Err(ParseHttpError::ParseBodyError(
+            "Invalid chunk extension".to_string(),
+        ));
+    }
+
+    Ok((size, extensions))
+}
+
+/// Parses a token or quoted-string, returning it unescaped with the rest of the input.
+fn parse_extension_value(input: &str) -> Result<(String, &str), ParseHttpError> {
+    if let Some(quoted) = input.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => return Ok((value, &quoted[index + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+        return Err(ParseHttpError::ParseBodyError(
+            "Unterminated quoted chunk extension".to_string(),
+        ));
+    }
+    let end = input.bytes().position(|b| !is_tchar(b)).unwrap_or(input.len());
+    Ok((input[..end].to_string(), &input[end..]))
+}
+
+#[cfg(test)]
+mod test_chunked {
+    use super::{encode_chunk, write_chunk, Chunk, ChunkedBody, ChunkedDecoder};
+    use crate::http::http::{Header, HeaderKey};
+
+    #[test]
+    fn chunked_decode_test() {
+        let input = b"4;name=\"quoted \\\"value\\\"\";flag\r\nWiki\r\n7\r\npedia i\r\n0\r\nExpires: never\r\n\r\nrest";
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(decoder.decode(&input[..12]).unwrap(), None);
+        assert_eq!(decoder.decode(&input[..40]).unwrap(), None);
+        let length = decoder.decode(input).unwrap().unwrap();
+        assert_eq!(&input[length..], b"rest");
+
+        let body = decoder.into_body();
+        assert_eq!(body.data(), b"Wikipedia i".to_vec());
+        assert_eq!(
+            body.chunks[0].extensions,
+            vec![
+                ("name".to_string(), Some("quoted \"value\"".to_string())),
+                ("flag".to_string(), None)
+            ]
+        );
+        assert_eq!(
+            body.trailers
+                .get(&HeaderKey::new("Expires".to_string()).unwrap())
+                .unwrap()
+                .as_str(),
+            "never"
+        );
+    }
+
+    #[test]
+    fn chunked_encode_test() {
+        let mut body = ChunkedBody::from_data(b"hello world", 6);
+        let mut chunk = Chunk::new(b"!".to_vec());
+        chunk.add_extension("note".to_string(), Some("a b".to_string()));
+        body.add_chunk(chunk)
+            .add_trailer(Header::new("Expires".to_string(), "never".to_string()).unwrap());
+
+        let encoded = body.encode().unwrap();
+        assert_eq!(
+            String::from_utf8(encoded.clone()).unwrap(),
+            "6\r\nhello \r\n5\r\nworld\r\n1;note=\"a b\"\r\n!\r\n0\r\nExpires: never\r\n\r\n"
+        );
+
+        let (decoded, length) = ChunkedBody::parse(&encoded).unwrap();
+        assert_eq!(length, encoded.len());
+        assert_eq!(decoded.data(), b"hello world!".to_vec());
+        assert_eq!(decoded.chunks[2].extensions, body.chunks[2].extensions);
+
+        let mut chunk = Chunk::new(b"!".to_vec());
+        chunk.add_extension("a\r\n0\r\n\r\n".to_string(), None);
+        let mut written = Vec::new();
+        assert!(write_chunk(&mut written, &chunk).is_err());
+        assert!(written.is_empty());
+        let mut chunk = Chunk::new(b"!".to_vec());
+        chunk.add_extension("note".to_string(), Some("a\r\nb".to_string()));
+        assert!(write_chunk(&mut written, &chunk).is_err());
+        assert!(matches!(encode_chunk(&chunk), crate::Result::Err(_)));
+        body.add_chunk(chunk);
+        assert!(matches!(body.encode(), crate::Result::Err(_)));
+    }
+
+    #[test]
+    fn invalid_chunk_size_test() {
+        let mut decoder = ChunkedDecoder::new();
+        assert!(matches!(
+            decoder.decode(b"zz\r\nhello\r\n"),
+            crate::Result::Err(_)
+        ));
+        let mut decoder = ChunkedDecoder::new();
+        assert!(matches!(
+            decoder.decode(b"FFFFFFFFFFFFFFFF\r\nhello\r\n"),
+            crate::Result::Err(_)
+        ));
+        let mut decoder = ChunkedDecoder::new();
+        assert_eq!(decoder.decode(b"7FFFFFFFFFFFFFFF\r\nhello\r\n").unwrap(), None);
+    }
+}
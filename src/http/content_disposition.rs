@@ -0,0 +1,204 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::http::ParseHttpError;
+use super::media_type::{check_parameter, is_ows, is_token, parse_parameters, write_parameters};
+use super::uri::percent_decode;
+
+/// A parsed Content-Disposition field (RFC 6266), as used by
+/// `multipart/form-data` sections (RFC 7578 section 4.2).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentDisposition {
+    disposition_type: String,
+    parameters: Vec<(String, String)>,
+}
+impl ContentDisposition {
+    pub fn new(disposition_type: &str) -> Result<ContentDisposition, ParseHttpError> {
+        if !is_token(disposition_type) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid disposition type: {}",
+                disposition_type
+            )));
+        }
+        Ok(ContentDisposition {
+            disposition_type: disposition_type.to_ascii_lowercase(),
+            parameters: Vec::new(),
+        })
+    }
+
+    /// A `form-data` disposition for the named field.
+    pub fn form_data(name: &str) -> Result<ContentDisposition, ParseHttpError> {
+        let mut disposition = ContentDisposition::new("form-data")?;
+        disposition.set_param("name", name)?;
+        Ok(disposition)
+    }
+
+    pub fn parse(value: &str) -> Result<ContentDisposition, ParseHttpError> {
+        let end = value.find(';').unwrap_or(value.len());
+        let mut disposition = ContentDisposition::new(value[..end].trim_matches(is_ows))?;
+        disposition.parameters = parse_parameters(&value[end..], value)?;
+        Ok(disposition)
+    }
+
+    pub fn disposition_type(&self) -> &str {
+        &self.disposition_type
+    }
+
+    pub fn is_form_data(&self) -> bool {
+        self.disposition_type == "form-data"
+    }
+
+    pub fn is_attachment(&self) -> bool {
+        self.disposition_type == "attachment"
+    }
+
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+
+    /// The raw value of the first parameter with this name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a parameter, replacing any existing value. Fails for values
+    /// that cannot be written, see `check_parameter`, and for names ending
+    /// in `*` unless the value is a well-formed ext-value.
+    pub fn set_param(&mut self, name: &str, value: &str) -> Result<(), ParseHttpError> {
+        check_parameter(name, value)?;
+        if name.ends_with('*') {
+            check_ext_value(value)?;
+        }
+        let name = name.to_ascii_lowercase();
+        self.parameters.retain(|(key, _)| key != &name);
+        self.parameters.push((name, value.to_string()));
+        Ok(())
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.param("name")
+    }
+
+    /// The filename, preferring the RFC 5987 encoded `filename*` parameter
+    /// over `filename` when it can be decoded (RFC 6266 section 4.3).
+    pub fn filename(&self) -> Option<String> {
+        if let Some(value) = self.param("filename*") {
+            if let Ok(decoded) = decode_ext_value(value) {
+                return Some(decoded);
+            }
+        }
+        self.param("filename").map(|value| value.to_string())
+    }
+}
+impl Display for ContentDisposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Form-data consumers commonly expect quoted names (RFC 7578 section 4.2)
+        f.write_str(&self.disposition_type)?;
+        write_parameters(f, &self.parameters, true)
+    }
+}
+
+/// Decodes an RFC 5987 ext-value such as `UTF-8''%e2%82%ac%20rates`.
+/// Only the UTF-8 and ISO-8859-1 charsets are supported.
+pub fn decode_ext_value(value: &str) -> Result<String, ParseHttpError> {
+    let mut parts = value.splitn(3, '\'');
+    let (Some(charset), Some(_language), Some(encoded)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid ext-value: {}",
+            value
+        )));
+    };
+    let bytes = percent_decode(encoded)?;
+    if charset.eq_ignore_ascii_case("UTF-8") {
+        match String::from_utf8(bytes) {
+            std::result::Result::Ok(decoded) => Ok(decoded),
+            std::result::Result::Err(_) => Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid utf-8 in ext-value: {}",
+                value
+            ))),
+        }
+    } else if charset.eq_ignore_ascii_case("ISO-8859-1") {
+        Ok(bytes.into_iter().map(char::from).collect())
+    } else {
+        Err(ParseHttpError::ParseHeaderError(format!(
+            "Unsupported charset in ext-value: {}",
+            charset
+        )))
+    }
+}
+
+/// Checks `charset "'" [ language ] "'" value-chars` (RFC 8187 section
+/// 3.2.1), where value-chars are attr-chars or percent escapes, and that
+/// `decode_ext_value` can read it.
+fn check_ext_value(value: &str) -> Result<(), ParseHttpError> {
+    let is_attr_char = |b: u8| b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b);
+    let mut parts = value.splitn(3, '\'');
+    let well_formed = match (parts.next(), parts.next(), parts.next()) {
+        (Some(charset), Some(language), Some(encoded)) => {
+            !charset.is_empty()
+                && charset.bytes().all(is_attr_char)
+                && language.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                && encoded.split('%').skip(1).all(|escape| {
+                    escape.len() >= 2 && escape.as_bytes()[..2].iter().all(u8::is_ascii_hexdigit)
+                })
+                && encoded.bytes().all(|b| b == b'%' || is_attr_char(b))
+        }
+        _ => false,
+    };
+    if !well_formed {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid ext-value: {}",
+            value
+        )));
+    }
+    decode_ext_value(value)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_content_disposition {
+    use super::ContentDisposition;
+    use crate::Result::Err;
+
+    #[test]
+    fn content_disposition_parse_test() {
+        let disposition = ContentDisposition::parse(
+            "form-data; name=\"upload\"; filename=\"a \\\"b\\\".txt\"; filename*=UTF-8''%e2%82%ac%20rates.txt",
+        )
+        .unwrap();
+        assert!(disposition.is_form_data());
+        assert_eq!(disposition.name(), Some("upload"));
+        assert_eq!(disposition.param("filename"), Some("a \"b\".txt"));
+        assert_eq!(disposition.filename(), Some("\u{20ac} rates.txt".to_string()));
+
+        let disposition =
+            ContentDisposition::parse("Attachment; FILENAME*=iso-8859-1'en'%A3%20rates; filename=x")
+                .unwrap();
+        assert!(disposition.is_attachment());
+        assert_eq!(disposition.filename(), Some("\u{a3} rates".to_string()));
+
+        let disposition = ContentDisposition::parse("form-data; name=x; filename*=bogus").unwrap();
+        assert_eq!(disposition.filename(), None);
+
+        assert!(matches!(ContentDisposition::form_data("a\r\nX-Injected: 1"), Err(_)));
+        let mut disposition = ContentDisposition::form_data("upload").unwrap();
+        assert!(matches!(disposition.set_param("filename", "a\nb"), Err(_)));
+        disposition.set_param("filename", "a.txt").unwrap();
+        assert_eq!(disposition.to_string(), "form-data; name=\"upload\"; filename=\"a.txt\"");
+
+        disposition.set_param("filename*", "UTF-8''%e2%82%ac.txt").unwrap();
+        assert_eq!(
+            disposition.to_string(),
+            "form-data; name=\"upload\"; filename=\"a.txt\"; filename*=UTF-8''%e2%82%ac.txt"
+        );
+        assert_eq!(disposition.filename(), Some("\u{20ac}.txt".to_string()));
+        assert!(matches!(disposition.set_param("filename*", "a.txt"), Err(_)));
+        assert!(matches!(disposition.set_param("filename*", "UTF-8''a b"), Err(_)));
+        assert!(matches!(disposition.set_param("filename*", "UTF-8''%e2%8"), Err(_)));
+        assert!(matches!(disposition.set_param("filename*", "UTF-8''%ff"), Err(_)));
+    }
+}
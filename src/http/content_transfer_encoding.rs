@@ -0,0 +1,281 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::http::ParseHttpError;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// RFC 2045 limits encoded lines to 76 characters
+const MAX_LINE_LENGTH: usize = 76;
+
+/// The Content-Transfer-Encoding of a MIME part (RFC 2045 section 6).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentTransferEncoding {
+    SevenBit,
+    EightBit,
+    Binary,
+    Base64,
+    QuotedPrintable,
+}
+impl ContentTransferEncoding {
+    pub fn parse(value: &str) -> Result<ContentTransferEncoding, ParseHttpError> {
+        let value = value.trim();
+        for encoding in [
+            ContentTransferEncoding::SevenBit,
+            ContentTransferEncoding::EightBit,
+            ContentTransferEncoding::Binary,
+            ContentTransferEncoding::Base64,
+            ContentTransferEncoding::QuotedPrintable,
+        ] {
+            if value.eq_ignore_ascii_case(encoding.as_str()) {
+                return Ok(encoding);
+            }
+        }
+        Err(ParseHttpError::ParseHeaderError(format!(
+            "Unknown Content-Transfer-Encoding: {}",
+            value
+        )))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentTransferEncoding::SevenBit => "7bit",
+            ContentTransferEncoding::EightBit => "8bit",
+            ContentTransferEncoding::Binary => "binary",
+            ContentTransferEncoding::Base64 => "base64",
+            ContentTransferEncoding::QuotedPrintable => "quoted-printable",
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            ContentTransferEncoding::Base64 => base64_encode(data).into_bytes(),
+            ContentTransferEncoding::QuotedPrintable => quoted_printable_encode(data).into_bytes(),
+            _ => data.to_vec(),
+        }
+    }
+
+    pub fn decode(&self, data: &[u8]) -> Result<Vec<u8>, ParseHttpError> {
+        match self {
+            ContentTransferEncoding::Base64 => base64_decode(data),
+            ContentTransferEncoding::QuotedPrintable => quoted_printable_decode(data),
+            _ => Ok(data.to_vec()),
+        }
+    }
+}
+impl Display for ContentTransferEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Base64 with padding, broken into CRLF separated lines.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len() * 4 / 3 + 4);
+    let mut line_length = 0;
+    for group in data.chunks(3) {
+        if line_length == MAX_LINE_LENGTH {
+            result.push_str("\r\n");
+            line_length = 0;
+        }
+        let bytes = [
+            group[0],
+            group.get(1).copied().unwrap_or(0),
+            group.get(2).copied().unwrap_or(0),
+        ];
+        let indices = [
+            bytes[0] >> 2,
+            (bytes[0] & 0x03) << 4 | bytes[1] >> 4,
+            (bytes[1] & 0x0f) << 2 | bytes[2] >> 6,
+            bytes[2] & 0x3f,
+        ];
+        for (position, index) in indices.iter().enumerate() {
+            if position <= group.len() {
+                result.push(BASE64_ALPHABET[*index as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+        line_length += 4;
+    }
+    result
+}
+
+/// Decodes base64, ignoring line breaks and other whitespace.
+pub fn base64_decode(data: &[u8]) -> Result<Vec<u8>, ParseHttpError> {
+    let mut result = Vec::with_capacity(data.len() * 3 / 4);
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    let mut padding = 0;
+    for &byte in data {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => {
+                return Err(ParseHttpError::ParseBodyError(format!(
+                    "Invalid base64 character: {:?}",
+                    byte as char
+                )))
+            }
+        };
+        if padding > 0 {
+            return Err(ParseHttpError::ParseBodyError(
+                "Base64 data after padding".to_string(),
+            ));
+        }
+        accumulator = accumulator << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((accumulator >> bits) as u8);
+            accumulator &= (1 << bits) - 1;
+        }
+    }
+    if bits >= 6 || padding > 2 {
+        return Err(ParseHttpError::ParseBodyError(
+            "Truncated base64 data".to_string(),
+        ));
+    }
+    Ok(result)
+}
+
+/// Quoted-printable (RFC 2045 section 6.7). CRLF pairs are kept as line
+/// breaks; everything else outside printable ASCII is escaped.
+pub fn quoted_printable_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len());
+    let mut line_length = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let byte = data[index];
+        if data[index..].starts_with(b"\r\n") {
+            result.push_str("\r\n");
+            line_length = 0;
+            index += 2;
+            continue;
+        }
+        // Whitespace at the end of a line would be stripped in transit
+        let at_line_end = index + 1 == data.len() || data[index + 1..].starts_with(b"\r\n");
+        let literal = match byte {
+            b' ' | b'\t' => !at_line_end,
+            b'=' => false,
+            33..=126 => true,
+            _ => false,
+        };
+        let width = if literal { 1 } else { 3 };
+        if line_length + width > MAX_LINE_LENGTH - 1 {
+            result.push_str("=\r\n");
+            line_length = 0;
+        }
+        if literal {
+            result.push(byte as char);
+        } else {
+            result.push_str(&format!("={:02X}", byte));
+        }
+        line_length += width;
+        index += 1;
+    }
+    result
+}
+
+pub fn quoted_printable_decode(data: &[u8]) -> Result<Vec<u8>, ParseHttpError> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut lines = data.split(|&b| b == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = trim_end_whitespace(line);
+        let (line, soft_break) = match line.strip_suffix(b"=") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let mut index = 0;
+        while index < line.len() {
+            if line[index] == b'=' {
+                let high = line.get(index + 1).and_then(|b| (*b as char).to_digit(16));
+                let low = line.get(index + 2).and_then(|b| (*b as char).to_digit(16));
+                match (high, low) {
+                    (Some(high), Some(low)) => result.push((high * 16 + low) as u8),
+                    _ => {
+                        return Err(ParseHttpError::ParseBodyError(
+                            "Invalid quoted-printable escape".to_string(),
+                        ))
+                    }
+                }
+                index += 3;
+            } else {
+                result.push(line[index]);
+                index += 1;
+            }
+        }
+
+        if !soft_break && lines.peek().is_some() {
+            result.extend_from_slice(b"\r\n");
+        }
+    }
+    Ok(result)
+}
+
+fn trim_end_whitespace(line: &[u8]) -> &[u8] {
+    let end = line
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    &line[..end]
+}
+
+#[cfg(test)]
+mod test_content_transfer_encoding {
+    use super::{
+        base64_decode, base64_encode, quoted_printable_decode, quoted_printable_encode,
+        ContentTransferEncoding,
+    };
+    use crate::Result::Err;
+
+    #[test]
+    fn base64_test() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_decode(b"Zm9v\r\nYmE=").unwrap(), b"fooba");
+
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = base64_encode(&data);
+        assert!(encoded.split("\r\n").all(|line| line.len() <= 76));
+        assert_eq!(base64_decode(encoded.as_bytes()).unwrap(), data);
+
+        assert!(matches!(base64_decode(b"Zm9v!"), Err(_)));
+        assert!(matches!(base64_decode(b"Zg=a"), Err(_)));
+        assert!(matches!(base64_decode(b"Z"), Err(_)));
+    }
+
+    #[test]
+    fn quoted_printable_test() {
+        let text = "caf\u{e9} = ok \r\nnext line".as_bytes();
+        let encoded = quoted_printable_encode(text);
+        assert_eq!(encoded, "caf=C3=A9 =3D ok=20\r\nnext line");
+        assert_eq!(quoted_printable_decode(encoded.as_bytes()).unwrap(), text);
+
+        let long = "x".repeat(100);
+        let encoded = quoted_printable_encode(long.as_bytes());
+        assert!(encoded.split("\r\n").all(|line| line.len() <= 76));
+        assert_eq!(quoted_printable_decode(encoded.as_bytes()).unwrap(), long.as_bytes());
+
+        assert_eq!(quoted_printable_decode(b"soft=\r\nbreak  \r\n").unwrap(), b"softbreak\r\n");
+        assert!(matches!(quoted_printable_decode(b"bad=G1"), Err(_)));
+
+        assert_eq!(
+            ContentTransferEncoding::parse("Quoted-Printable").unwrap(),
+            ContentTransferEncoding::QuotedPrintable
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::http::ParseHttpError;
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const LONG_DAY_NAMES: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// A timestamp as carried by Date, Last-Modified and the conditional
+/// request headers (RFC 9110 section 5.6.7). HTTP dates have a resolution
+/// of one second and cannot precede 1970.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate(u64);
+impl HttpDate {
+    pub fn now() -> HttpDate {
+        HttpDate::from(SystemTime::now())
+    }
+
+    pub fn from_unix_secs(secs: u64) -> HttpDate {
+        HttpDate(secs)
+    }
+
+    pub fn as_unix_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// Parses the preferred IMF-fixdate format as well as the obsolete
+    /// RFC 850 and asctime formats recipients must still accept.
+    pub fn parse(value: &str) -> Result<HttpDate, ParseHttpError> {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        let parsed = match fields.as_slice() {
+            // Sun, 06 Nov 1994 08:49:37 GMT
+            [day_name, day, month, year, time, "GMT"]
+                if day_name.strip_suffix(',').is_some_and(|name| DAY_NAMES.contains(&name))
+                    && day.len() == 2
+                    && year.len() == 4 =>
+            {
+                date_fields(day, month, year, time)
+            }
+            // Sunday, 06-Nov-94 08:49:37 GMT
+            [day_name, date, time, "GMT"]
+                if day_name
+                    .strip_suffix(',')
+                    .is_some_and(|name| LONG_DAY_NAMES.contains(&name)) =>
+            {
+                match date.split('-').collect::<Vec<&str>>().as_slice() {
+                    [day, month, year] if day.len() == 2 && year.len() == 2 => {
+                        date_fields(day, month, year, time).map(expand_two_digit_year)
+                    }
+                    _ => None,
+                }
+            }
+            // Sun Nov  6 08:49:37 1994
+            [day_name, month, day, time, year]
+                if DAY_NAMES.contains(day_name) && !day.is_empty() && day.len() <= 2 && year.len() == 4 =>
+            {
+                date_fields(day, month, year, time)
+            }
+            _ => None,
+        };
+        match parsed.and_then(|fields| fields.to_unix_secs()) {
+            Some(secs) => Ok(HttpDate(secs)),
+            None => Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid HTTP date: {}",
+                value
+            ))),
+        }
+    }
+}
+impl Display for HttpDate {
+    /// Formats as IMF-fixdate, the only format senders may generate.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0 / SECONDS_PER_DAY;
+        let secs = self.0 % SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday
+        let weekday = ((days + 4) % 7) as usize;
+        write!(
+            f,
+            "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+            DAY_NAMES[weekday],
+            day,
+            MONTH_NAMES[month as usize - 1],
+            year,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+impl From<SystemTime> for HttpDate {
+    /// Truncates to whole seconds; times before 1970 become the epoch.
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(UNIX_EPOCH) {
+            std::result::Result::Ok(duration) => HttpDate(duration.as_secs()),
+            std::result::Result::Err(_) => HttpDate(0),
+        }
+    }
+}
+impl From<HttpDate> for SystemTime {
+    fn from(date: HttpDate) -> Self {
+        UNIX_EPOCH + Duration::from_secs(date.0)
+    }
+}
+
+struct DateFields {
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+}
+impl DateFields {
+    fn to_unix_secs(&self) -> Option<u64> {
+        if self.year < 1970
+            || !(1..=12).contains(&self.month)
+            || self.day == 0
+            || self.day > days_in_month(self.year, self.month)
+            || self.hour > 23
+            || self.minute > 59
+            // 60 allows for a leap second
+            || self.second > 60
+        {
+            return None;
+        }
+        let days = days_from_civil(self.year, self.month, self.day);
+        Some(days * SECONDS_PER_DAY + self.hour * 3600 + self.minute * 60 + self.second)
+    }
+}
+
+fn date_fields(day: &str, month: &str, year: &str, time: &str) -> Option<DateFields> {
+    let month = MONTH_NAMES.iter().position(|name| *name == month)? as u64 + 1;
+    let [hour, minute, second] = match time.split(':').collect::<Vec<&str>>().as_slice() {
+        [hour, minute, second] if hour.len() == 2 && minute.len() == 2 && second.len() == 2 => {
+            [*hour, *minute, *second]
+        }
+        _ => return None,
+    };
+    Some(DateFields {
+        year: digits(year)?,
+        month,
+        day: digits(day)?,
+        hour: digits(hour)?,
+        minute: digits(minute)?,
+        second: digits(second)?,
+    })
+}
+
+fn digits(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// A two-digit year that would lie more than 50 years in the future is
+/// taken to be in the past century (RFC 9110 section 5.6.7).
+fn expand_two_digit_year(mut fields: DateFields) -> DateFields {
+    let (current_year, _, _) = civil_from_days(HttpDate::now().0 / SECONDS_PER_DAY);
+    let mut year = current_year - current_year % 100 + fields.year;
+    if year > current_year + 50 {
+        year -= 100;
+    }
+    fields.year = year;
+    fields
+}
+
+fn is_leap_year(year: u64) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar,
+/// for years from 1970 on.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Count from 0000-03-01 so the leap day ends the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = (shifted_month + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test_date {
+    use super::HttpDate;
+    use crate::Result::Err;
+
+    #[test]
+    fn http_date_test() {
+        let date = HttpDate::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        assert_eq!(date.as_unix_secs(), 784111777);
+        assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(HttpDate::parse("Sunday, 06-Nov-94 08:49:37 GMT").unwrap(), date);
+        assert_eq!(HttpDate::parse("Sun Nov  6 08:49:37 1994").unwrap(), date);
+
+        let leap = HttpDate::parse("Thu, 29 Feb 2024 23:59:59 GMT").unwrap();
+        assert_eq!(leap.to_string(), "Thu, 29 Feb 2024 23:59:59 GMT");
+        assert_eq!(HttpDate::from_unix_secs(0).to_string(), "Thu, 01 Jan 1970 00:00:00 GMT");
+
+        assert!(matches!(HttpDate::parse("Thu, 29 Feb 2023 00:00:00 GMT"), Err(_)));
+        assert!(matches!(HttpDate::parse("Sun, 06 Nov 1994 08:49:37 UTC"), Err(_)));
+        assert!(matches!(HttpDate::parse("Sun, 6 Nov 1994 08:49:37 GMT"), Err(_)));
+        assert!(matches!(HttpDate::parse("Sun, 06 Nov 1994 24:00:00 GMT"), Err(_)));
+        assert!(matches!(HttpDate::parse("Wed, 31 Dec 1969 23:59:59 GMT"), Err(_)));
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::Result;
+
+use super::http::{Header, HeaderCase, HeaderKey, HeaderValue, ParseHttpError};
+use super::typed_header::TypedHeader;
+
+/// Multi-value header collection that keeps fields in the order they were
+/// added, so repeated fields like `Set-Cookie` survive a round trip.
+pub struct HeaderMap {
+    entries: Vec<(HeaderKey, HeaderValue)>,
+    indices: HashMap<HeaderKey, Vec<usize>>,
+}
+impl Clone for HeaderMap {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            indices: self.indices.clone(),
+        }
+    }
+}
+impl Default for HeaderMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> HeaderMap {
+        HeaderMap {
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Number of fields, counting every value of a repeated field.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &HeaderKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Returns the first value of the field.
+    pub fn get(&self, key: &HeaderKey) -> Option<&HeaderValue> {
+        let index = *self.indices.get(key)?.first()?;
+        Some(&self.entries[index].1)
+    }
+
+    pub fn get_mut(&mut self, key: &HeaderKey) -> Option<&mut HeaderValue> {
+        let index = *self.indices.get(key)?.first()?;
+        Some(&mut self.entries[index].1)
+    }
+
+    /// Returns every value of the field in the order they were added.
+    pub fn get_all<'a>(&'a self, key: &HeaderKey) -> impl Iterator<Item = &'a HeaderValue> + 'a {
+        self.indices
+            .get(key)
+            .map(|indices| indices.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .map(move |&index| &self.entries[index].1)
+    }
+
+    /// Adds a value, keeping the values already present for the field.
+    pub fn append(&mut self, key: HeaderKey, value: HeaderValue) {
+        self.indices
+            .entry(key.clone())
+            .or_default()
+            .push(self.entries.len());
+        self.entries.push((key, value));
+    }
+
+    /// Sets the field to a single value. The value takes the position of the
+    /// first existing one, and the previous first value is returned.
+    pub fn insert(&mut self, key: HeaderKey, value: HeaderValue) -> Option<HeaderValue> {
+        let Some(indices) = self.indices.get(&key) else {
+            self.append(key, value);
+            return None;
+        };
+        let first = indices[0];
+        let previous = std::mem::replace(&mut self.entries[first].1, value);
+        if indices.len() > 1 {
+            let mut position = 0;
+            self.entries.retain(|(entry_key, _)| {
+                let keep = position == first || entry_key != &key;
+                position += 1;
+                keep
+            });
+            self.reindex();
+        }
+        Some(previous)
+    }
+
+    /// Removes every value of the field, returning the first one.
+    pub fn remove(&mut self, key: &HeaderKey) -> Option<HeaderValue> {
+        let indices = self.indices.remove(key)?;
+        let first = indices[0];
+        let mut previous = None;
+        let entries = std::mem::take(&mut self.entries);
+        for (position, (entry_key, value)) in entries.into_iter().enumerate() {
+            if position == first {
+                previous = Some(value);
+            } else if &entry_key != key {
+                self.entries.push((entry_key, value));
+            }
+        }
+        self.reindex();
+        previous
+    }
+
+    /// The field parsed as `H`, or `None` when it is absent.
+    pub fn typed_get<H: TypedHeader>(&self) -> Option<Result<H, ParseHttpError>> {
+        let key = H::name();
+        if !self.contains_key(&key) {
+            return None;
+        }
+        Some(H::decode(self.get_all(&key).map(|value| value.as_str())))
+    }
+
+    /// Replaces every value of the field with the encoding of `header`.
+    pub fn typed_insert<H: TypedHeader>(&mut self, header: H) -> Result<(), ParseHttpError> {
+        let value = header.encode()?;
+        self.insert(H::name(), value);
+        Result::Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.indices.clear();
+    }
+
+    /// Iterates over all fields in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderKey, &HeaderValue)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    /// Iterates over the distinct field names in order of first appearance.
+    pub fn keys(&self) -> impl Iterator<Item = &HeaderKey> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(position, (key, _))| self.indices[key][0] == *position)
+            .map(|(_, (key, _))| key)
+    }
+
+    /// Serializes every field as a `name: value` line terminated by CRLF.
+    pub fn encode(&self, case: HeaderCase) -> Vec<u8> {
+        let mut result = Vec::new();
+        // Writing to a Vec cannot fail
+        let _ = self.write_to(&mut result, case);
+        result
+    }
+
+    /// Writes each field line followed by CRLF, without the empty line
+    /// that ends a header section.
+    pub fn write_to(&self, w: &mut impl Write, case: HeaderCase) -> io::Result<()> {
+        for (key, value) in &self.entries {
+            key.write_cased(w, case)?;
+            w.write_all(b": ")?;
+            w.write_all(value.as_str().as_bytes())?;
+            w.write_all(b"\r\n")?;
+        }
+        Ok(())
+    }
+
+    fn reindex(&mut self) {
+        self.indices.clear();
+        for (position, (key, _)) in self.entries.iter().enumerate() {
+            self.indices.entry(key.clone()).or_default().push(position);
+        }
+    }
+}
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a HeaderKey, &'a HeaderValue);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (HeaderKey, HeaderValue)>,
+        fn(&'a (HeaderKey, HeaderValue)) -> (&'a HeaderKey, &'a HeaderValue),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+impl IntoIterator for HeaderMap {
+    type Item = (HeaderKey, HeaderValue);
+    type IntoIter = std::vec::IntoIter<(HeaderKey, HeaderValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+impl Extend<(HeaderKey, HeaderValue)> for HeaderMap {
+    fn extend<T: IntoIterator<Item = (HeaderKey, HeaderValue)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.append(key, value);
+        }
+    }
+}
+impl Extend<Header> for HeaderMap {
+    fn extend<T: IntoIterator<Item = Header>>(&mut self, iter: T) {
+        for header in iter {
+            self.append(header.key, header.value);
+        }
+    }
+}
+impl FromIterator<(HeaderKey, HeaderValue)> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = (HeaderKey, HeaderValue)>>(iter: T) -> Self {
+        let mut result = HeaderMap::new();
+        result.extend(iter);
+        result
+    }
+}
+impl FromIterator<Header> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = Header>>(iter: T) -> Self {
+        let mut result = HeaderMap::new();
+        result.extend(iter);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test_header_map {
+    use super::HeaderMap;
+    use crate::http::http::{HeaderKey, HeaderValue};
+
+    fn key(name: &str) -> HeaderKey {
+        HeaderKey::new(name.to_string()).unwrap()
+    }
+
+    fn value(value: &str) -> HeaderValue {
+        HeaderValue::new(value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn header_map_order_test() {
+        let mut headers = HeaderMap::new();
+        headers.append(key("Set-Cookie"), value("a=1"));
+        headers.append(key("X-Custom"), value("one"));
+        headers.append(key("X-Other"), value("two"));
+        headers.append(key("Set-Cookie"), value("b=2"));
+
+        assert_eq!(headers.len(), 4);
+        let cookies: Vec<&str> = headers
+            .get_all(&key("Set-Cookie"))
+            .map(|value| value.as_str())
+            .collect();
+        assert_eq!(cookies, vec!["a=1", "b=2"]);
+        assert_eq!(headers.get(&key("X-Other")).unwrap().as_str(), "two");
+
+        let names: Vec<String> = headers.keys().map(|key| key.to_string()).collect();
+        assert_eq!(names, vec!["Set-Cookie", "X-Custom", "X-Other"]);
+    }
+
+    #[test]
+    fn header_map_insert_remove_test() {
+        let mut headers = HeaderMap::new();
+        headers.append(key("Via"), value("1.1 a"));
+        headers.append(key("Host"), value("example.com"));
+        headers.append(key("Via"), value("1.1 b"));
+
+        let previous = headers.insert(key("Via"), value("1.1 c")).unwrap();
+        assert_eq!(previous.as_str(), "1.1 a");
+        let order: Vec<String> = headers
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value.as_str()))
+            .collect();
+        assert_eq!(order, vec!["Via: 1.1 c", "Host: example.com"]);
+
+        assert_eq!(headers.remove(&key("Via")).unwrap().as_str(), "1.1 c");
+        assert!(!headers.contains_key(&key("Via")));
+        assert_eq!(headers.get(&key("Host")).unwrap().as_str(), "example.com");
+        assert_eq!(headers.get_all(&key("Via")).count(), 0);
+    }
+}
@@ -1,8 +1,16 @@
 use crate::Result::{self, Err, Ok};
+use super::chunked::ChunkedBody;
+use super::content_disposition::ContentDisposition;
+use super::content_transfer_encoding::ContentTransferEncoding;
+use super::header_map::HeaderMap;
+use super::media_type::{is_token, MediaType};
+use super::multipart::{BodyPart, Multipart, MultipartEvent, MultipartParser};
+use super::status::StatusCode;
+use super::urlencoded::{is_urlencoded, UrlEncoded};
 use core::str;
-use std::collections::HashMap;
 use std::fmt::{self, Display};
 use std::hash::Hash;
+use std::io::{self, Write};
 use std::result;
 use std::str::{FromStr, Utf8Error};
 use std::string::FromUtf8Error;
@@ -17,6 +25,12 @@ pub enum ParseHttpError {
     UnknownString(String),
     InvalidHttpMethod,
     FormdataBoundaryNotFound,
+    IncompleteMessage,
+    IoError(String),
+    StartLineTooLong(usize),
+    TooManyHeaders(usize),
+    HeadersTooLarge(usize),
+    BodyTooLarge(usize),
 }
 impl PartialEq for ParseHttpError {
     fn eq(&self, other: &Self) -> bool {
@@ -24,6 +38,10 @@ impl PartialEq for ParseHttpError {
             (Self::ParseHeaderError(l0), Self::ParseHeaderError(r0)) => l0 == r0,
             (Self::ParseFormDataError(l0), Self::ParseFormDataError(r0)) => l0 == r0,
             (Self::UnknownString(l0), Self::UnknownString(r0)) => l0 == r0,
+            (Self::StartLineTooLong(l0), Self::StartLineTooLong(r0)) => l0 == r0,
+            (Self::TooManyHeaders(l0), Self::TooManyHeaders(r0)) => l0 == r0,
+            (Self::HeadersTooLarge(l0), Self::HeadersTooLarge(r0)) => l0 == r0,
+            (Self::BodyTooLarge(l0), Self::BodyTooLarge(r0)) => l0 == r0,
             _ => false,
         }
     }
@@ -59,7 +77,28 @@ impl ParseHttpError {
             ParseHttpError::UnknownString(msg) => format!("Unknown String: {}", msg),
             ParseHttpError::InvalidHttpMethod => "Invalid HTTP Method".to_string(),
             ParseHttpError::FormdataBoundaryNotFound => "Formdata Boundary Not Found".to_string(),
+            ParseHttpError::IncompleteMessage => "Incomplete Message".to_string(),
+            ParseHttpError::IoError(msg) => format!("IO Error: {}", msg),
             ParseHttpError::ParseError(msg) => format!("Parse Error: {}", msg),
+            ParseHttpError::StartLineTooLong(limit) => {
+                format!("Start line exceeds {} bytes", limit)
+            }
+            ParseHttpError::TooManyHeaders(limit) => format!("More than {} headers", limit),
+            ParseHttpError::HeadersTooLarge(limit) => format!("Headers exceed {} bytes", limit),
+            ParseHttpError::BodyTooLarge(limit) => format!("Body exceeds {} bytes", limit),
+        }
+    }
+
+    /// The status a server should answer a request that failed to parse
+    /// with. Exceeded limits get their own codes, anything else is a 400.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ParseHttpError::StartLineTooLong(_) => StatusCode::URI_TOO_LONG,
+            ParseHttpError::TooManyHeaders(_) | ParseHttpError::HeadersTooLarge(_) => {
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+            }
+            ParseHttpError::BodyTooLarge(_) => StatusCode::CONTENT_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
         }
     }
 }
@@ -74,34 +113,71 @@ impl Clone for ParseHttpError{
             Self::UnknownString(arg0) => Self::UnknownString(arg0.clone()),
             Self::InvalidHttpMethod => Self::InvalidHttpMethod,
             Self::FormdataBoundaryNotFound => Self::FormdataBoundaryNotFound,
+            Self::IncompleteMessage => Self::IncompleteMessage,
+            Self::IoError(arg0) => Self::IoError(arg0.clone()),
+            Self::StartLineTooLong(arg0) => Self::StartLineTooLong(*arg0),
+            Self::TooManyHeaders(arg0) => Self::TooManyHeaders(*arg0),
+            Self::HeadersTooLarge(arg0) => Self::HeadersTooLarge(*arg0),
+            Self::BodyTooLarge(arg0) => Self::BodyTooLarge(*arg0),
         }
     }
 }
+impl From<io::Error> for ParseHttpError {
+    fn from(error: io::Error) -> Self {
+        ParseHttpError::IoError(error.to_string())
+    }
+}
+
+/// Converts the result of a write into this crate's `Result`.
+pub(crate) fn io_result<T: Clone>(result: io::Result<T>) -> Result<T, ParseHttpError> {
+    match result {
+        std::result::Result::Ok(value) => Ok(value),
+        std::result::Result::Err(error) => Err(error.into()),
+    }
+}
 
 macro_rules! define_headers {
-    ($($variant:ident => $name:expr),*) => {
-        #[derive(Debug, PartialEq)]
+    (@index $index:literal) => { Some($index) };
+    (@index) => { None };
+    ($($variant:ident => $name:literal $(@ $index:literal)?),* $(,)?) => {
+        #[derive(Debug, PartialEq, Eq, Hash)]
         #[allow(non_camel_case_types)]
         pub enum StandardHeaders {
             $($variant),*
         }
 
-        impl ToString for StandardHeaders {
-            fn to_string(&self) -> String {
+        impl StandardHeaders {
+            /// The registry spelling of the name.
+            pub fn as_str(&self) -> &'static str {
                 match self {
-                    $(StandardHeaders::$variant => $name.to_string()),*
+                    $(StandardHeaders::$variant => $name),*
                 }
             }
+
+            /// The index of the name in the HPACK static table (RFC 7541
+            /// appendix A), if it has an entry there.
+            pub fn hpack_index(&self) -> Option<usize> {
+                match self {
+                    $(StandardHeaders::$variant => define_headers!(@index $($index)?)),*
+                }
+            }
+        }
+
+        impl ToString for StandardHeaders {
+            fn to_string(&self) -> String {
+                self.as_str().to_string()
+            }
         }
 
         impl FromStr for StandardHeaders {
             type Err = ParseHttpError;
 
+            // Field names are case-insensitive (RFC 9110 section 5.1)
             fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-                match s {
-                    $($name => std::result::Result::Ok(StandardHeaders::$variant),)*
-                    _ => std::result::Result::Err(ParseHttpError::ParseHeaderError(s.to_string())),
-                }
+                $(if s.eq_ignore_ascii_case($name) {
+                    return std::result::Result::Ok(StandardHeaders::$variant);
+                })*
+                std::result::Result::Err(ParseHttpError::ParseHeaderError(s.to_string()))
             }
         }
 
@@ -115,51 +191,146 @@ macro_rules! define_headers {
     };
 }
 
+// Permanent entries of the IANA HTTP Field Name Registry, plus the MIME
+// fields used inside multipart bodies and a few widespread X- fields.
+// `@ n` gives the entry's index in the HPACK static table.
 define_headers! {
     A_IM => "A-IM",
-    Accept => "Accept",
-    Accept_Charset => "Accept-Charset",
-    Accept_Encoding => "Accept-Encoding",
-    Accept_Language => "Accept-Language",
+    Accept => "Accept" @ 19,
+    Accept_CH => "Accept-CH",
+    Accept_Charset => "Accept-Charset" @ 15,
     Accept_Datetime => "Accept-Datetime",
-    Access_Control_Request_Method => "Access-Control-Request-Method",
+    Accept_Encoding => "Accept-Encoding" @ 16,
+    Accept_Language => "Accept-Language" @ 17,
+    Accept_Patch => "Accept-Patch",
+    Accept_Post => "Accept-Post",
+    Accept_Ranges => "Accept-Ranges" @ 18,
+    Access_Control_Allow_Credentials => "Access-Control-Allow-Credentials",
+    Access_Control_Allow_Headers => "Access-Control-Allow-Headers",
+    Access_Control_Allow_Methods => "Access-Control-Allow-Methods",
+    Access_Control_Allow_Origin => "Access-Control-Allow-Origin" @ 20,
+    Access_Control_Expose_Headers => "Access-Control-Expose-Headers",
+    Access_Control_Max_Age => "Access-Control-Max-Age",
     Access_Control_Request_Headers => "Access-Control-Request-Headers",
-    Authorization => "Authorization",
-    Cache_Control => "Cache-Control",
+    Access_Control_Request_Method => "Access-Control-Request-Method",
+    Age => "Age" @ 21,
+    Allow => "Allow" @ 22,
+    ALPN => "ALPN",
+    Alt_Svc => "Alt-Svc",
+    Alt_Used => "Alt-Used",
+    Authentication_Info => "Authentication-Info",
+    Authorization => "Authorization" @ 23,
+    Cache_Control => "Cache-Control" @ 24,
+    Cache_Status => "Cache-Status",
+    CDN_Cache_Control => "CDN-Cache-Control",
+    CDN_Loop => "CDN-Loop",
+    Clear_Site_Data => "Clear-Site-Data",
+    Close => "Close",
     Connection => "Connection",
-    Content_Disposition => "Content-Disposition",
-    Content_Length => "Content-Length",
-    Content_Type => "Content-Type",
-    Content_Transfer_Encoding=>"Content-Transfer-Encoding",
-    Cookie => "Cookie",
-    Date => "Date",
-    Expect => "Expect",
+    Content_Digest => "Content-Digest",
+    Content_Disposition => "Content-Disposition" @ 25,
+    Content_Encoding => "Content-Encoding" @ 26,
+    Content_ID => "Content-ID",
+    Content_Language => "Content-Language" @ 27,
+    Content_Length => "Content-Length" @ 28,
+    Content_Location => "Content-Location" @ 29,
+    Content_Range => "Content-Range" @ 30,
+    Content_Security_Policy => "Content-Security-Policy",
+    Content_Security_Policy_Report_Only => "Content-Security-Policy-Report-Only",
+    Content_Transfer_Encoding => "Content-Transfer-Encoding",
+    Content_Type => "Content-Type" @ 31,
+    Cookie => "Cookie" @ 32,
+    Cross_Origin_Embedder_Policy => "Cross-Origin-Embedder-Policy",
+    Cross_Origin_Opener_Policy => "Cross-Origin-Opener-Policy",
+    Cross_Origin_Resource_Policy => "Cross-Origin-Resource-Policy",
+    Date => "Date" @ 33,
+    DAV => "DAV",
+    Depth => "Depth",
+    Destination => "Destination",
+    Early_Data => "Early-Data",
+    ETag => "ETag" @ 34,
+    Expect => "Expect" @ 35,
+    Expires => "Expires" @ 36,
     Forwarded => "Forwarded",
-    From => "From",
-    Host => "Host",
-    If_Match => "If-Match",
-    If_Modified_Since => "If-Modified-Since",
-    If_None_Match => "If-None-Match",
-    If_Range => "If-Range",
-    If_Unmodified_Since => "If-Unmodified-Since",
-    Max_Forwards => "Max-Forwards",
+    From => "From" @ 37,
+    Host => "Host" @ 38,
+    HTTP2_Settings => "HTTP2-Settings",
+    If => "If",
+    If_Match => "If-Match" @ 39,
+    If_Modified_Since => "If-Modified-Since" @ 40,
+    If_None_Match => "If-None-Match" @ 41,
+    If_Range => "If-Range" @ 42,
+    If_Schedule_Tag_Match => "If-Schedule-Tag-Match",
+    If_Unmodified_Since => "If-Unmodified-Since" @ 43,
+    Keep_Alive => "Keep-Alive",
+    Last_Event_ID => "Last-Event-ID",
+    Last_Modified => "Last-Modified" @ 44,
+    Link => "Link" @ 45,
+    Location => "Location" @ 46,
+    Lock_Token => "Lock-Token",
+    Max_Forwards => "Max-Forwards" @ 47,
+    Memento_Datetime => "Memento-Datetime",
+    MIME_Version => "MIME-Version",
+    NEL => "NEL",
     Origin => "Origin",
+    Origin_Agent_Cluster => "Origin-Agent-Cluster",
+    Overwrite => "Overwrite",
+    Ping_From => "Ping-From",
+    Ping_To => "Ping-To",
     Pragma => "Pragma",
-    Proxy_Authorization => "Proxy-Authorization",
-    Range => "Range",
-    Referer => "Referer",
+    Prefer => "Prefer",
+    Preference_Applied => "Preference-Applied",
+    Priority => "Priority",
+    Proxy_Authenticate => "Proxy-Authenticate" @ 48,
+    Proxy_Authentication_Info => "Proxy-Authentication-Info",
+    Proxy_Authorization => "Proxy-Authorization" @ 49,
+    Proxy_Status => "Proxy-Status",
+    Range => "Range" @ 50,
+    Referer => "Referer" @ 51,
+    Referrer_Policy => "Referrer-Policy",
+    Refresh => "Refresh" @ 52,
+    Repr_Digest => "Repr-Digest",
+    Retry_After => "Retry-After" @ 53,
+    Schedule_Reply => "Schedule-Reply",
+    Schedule_Tag => "Schedule-Tag",
+    Sec_WebSocket_Accept => "Sec-WebSocket-Accept",
+    Sec_WebSocket_Extensions => "Sec-WebSocket-Extensions",
+    Sec_WebSocket_Key => "Sec-WebSocket-Key",
+    Sec_WebSocket_Protocol => "Sec-WebSocket-Protocol",
+    Sec_WebSocket_Version => "Sec-WebSocket-Version",
+    Server => "Server" @ 54,
+    Server_Timing => "Server-Timing",
+    Set_Cookie => "Set-Cookie" @ 55,
+    SLUG => "SLUG",
+    Strict_Transport_Security => "Strict-Transport-Security" @ 56,
+    Sunset => "Sunset",
     TE => "TE",
-    User_Agent => "User-Agent",
+    Timeout => "Timeout",
+    Timing_Allow_Origin => "Timing-Allow-Origin",
+    Trailer => "Trailer",
+    Transfer_Encoding => "Transfer-Encoding" @ 57,
     Upgrade => "Upgrade",
-    Via => "Via",
+    User_Agent => "User-Agent" @ 58,
+    Vary => "Vary" @ 59,
+    Via => "Via" @ 60,
+    Want_Content_Digest => "Want-Content-Digest",
+    Want_Repr_Digest => "Want-Repr-Digest",
     Warning => "Warning",
-    // Add additional headers here, in the same format
-    Content_Security_Policy => "Content-Security-Policy",
-    Strict_Transport_Security => "Strict-Transport-Security",
+    WWW_Authenticate => "WWW-Authenticate" @ 61,
     X_Content_Type_Options => "X-Content-Type-Options",
     X_Frame_Options => "X-Frame-Options",
-    X_XSS_Protection => "X-XSS-Protection"
-    // Add the rest as required
+    X_XSS_Protection => "X-XSS-Protection",
+}
+
+/// How header names are spelled when a message is serialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HeaderCase {
+    /// Registry spelling for standard headers, `Title-Case` for the rest.
+    TitleCase,
+    Lowercase,
+    /// The spelling the name was parsed or constructed with.
+    #[default]
+    Original,
 }
 
 pub struct CustomHeader(String);
@@ -170,29 +341,48 @@ impl Clone for CustomHeader{
 }
 impl PartialEq for CustomHeader {
     fn eq(&self, other: &Self) -> bool {
-        self.0 == other.0
+        self.0.eq_ignore_ascii_case(&other.0)
     }
 }
 
+/// A header name. Standard headers keep the spelling they were parsed
+/// with, `None` meaning the registry spelling. Comparison and hashing
+/// ignore case.
 pub enum HeaderKey {
-    StandardHeader(StandardHeaders),
+    StandardHeader(StandardHeaders, Option<String>),
     CustomHeader(CustomHeader),
 }
 impl Clone for HeaderKey {
     fn clone(&self) -> Self {
         match self {
-            Self::StandardHeader(arg0) => Self::StandardHeader(arg0.clone()),
+            Self::StandardHeader(arg0, arg1) => Self::StandardHeader(arg0.clone(), arg1.clone()),
             Self::CustomHeader(arg0) => Self::CustomHeader(arg0.clone()),
         }
     }
 }
+impl From<StandardHeaders> for HeaderKey {
+    fn from(value: StandardHeaders) -> Self {
+        HeaderKey::StandardHeader(value, None)
+    }
+}
 impl FromStr for HeaderKey {
     type Err = ParseHttpError;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if !is_token(s) {
+            return std::result::Result::Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid header name: {:?}",
+                s
+            )));
+        }
         match StandardHeaders::from_str(s) {
             std::result::Result::Ok(standard_header) => {
-                std::result::Result::Ok(HeaderKey::StandardHeader(standard_header))
+                let original = if standard_header.to_string() == s {
+                    None
+                } else {
+                    Some(s.to_string())
+                };
+                std::result::Result::Ok(HeaderKey::StandardHeader(standard_header, original))
             }
             std::result::Result::Err(_) => {
                 std::result::Result::Ok(HeaderKey::CustomHeader(CustomHeader { 0: s.to_string() }))
@@ -202,32 +392,17 @@ impl FromStr for HeaderKey {
 }
 impl Display for HeaderKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            HeaderKey::StandardHeader(standard_headers) => {
-                f.write_str(standard_headers.to_string().as_str())
-            }
-            HeaderKey::CustomHeader(custom_header) => f.write_str(&custom_header.0),
-        }
+        f.write_str(&self.to_cased_string(HeaderCase::Original))
     }
 }
 impl Into<Vec<u8>> for HeaderKey {
     fn into(self) -> Vec<u8> {
-        match self {
-            HeaderKey::StandardHeader(standard_headers) => {
-                standard_headers.to_string().as_bytes().to_vec()
-            }
-            HeaderKey::CustomHeader(custom_header) => custom_header.0.as_bytes().to_vec(),
-        }
+        self.to_cased_string(HeaderCase::Original).into_bytes()
     }
 }
 impl Into<Vec<u8>> for &HeaderKey {
     fn into(self) -> Vec<u8> {
-        match self {
-            HeaderKey::StandardHeader(standard_headers) => {
-                standard_headers.to_string().as_bytes().to_vec()
-            }
-            HeaderKey::CustomHeader(custom_header) => custom_header.0.as_bytes().to_vec(),
-        }
+        self.to_cased_string(HeaderCase::Original).into_bytes()
     }
 }
 impl Into<Result<HeaderKey, ParseHttpError>> for Vec<u8> {
@@ -240,20 +415,15 @@ impl Into<Result<HeaderKey, ParseHttpError>> for Vec<u8> {
                 ))
             }
         };
-        let result = match HeaderKey::from_str(s) {
-            std::result::Result::Ok(result) => result,
-            std::result::Result::Err(_) => unreachable!(),
-        };
-        Ok(result)
+        match HeaderKey::from_str(s) {
+            std::result::Result::Ok(result) => Ok(result),
+            std::result::Result::Err(e) => Err(e),
+        }
     }
 }
 impl Into<Result<HeaderKey, ParseHttpError>> for String {
     fn into(self) -> Result<HeaderKey, ParseHttpError> {
-        let result = match HeaderKey::from_str(self.as_str()) {
-            std::result::Result::Ok(result) => result,
-            std::result::Result::Err(_) => unreachable!(),
-        };
-        Ok(result)
+        HeaderKey::new(self)
     }
 }
 impl Into<Result<HeaderKey, ParseHttpError>> for &[u8] {
@@ -264,12 +434,21 @@ impl Into<Result<HeaderKey, ParseHttpError>> for &[u8] {
 impl Hash for HeaderKey {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         core::mem::discriminant(self).hash(state);
+        match self {
+            HeaderKey::StandardHeader(standard_header, _) => standard_header.hash(state),
+            HeaderKey::CustomHeader(custom_header) => {
+                for byte in custom_header.0.bytes() {
+                    state.write_u8(byte.to_ascii_lowercase());
+                }
+                state.write_usize(custom_header.0.len());
+            }
+        }
     }
 }
 impl PartialEq for HeaderKey {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Self::StandardHeader(l0), Self::StandardHeader(r0)) => l0 == r0,
+            (Self::StandardHeader(l0, _), Self::StandardHeader(r0, _)) => l0 == r0,
             (Self::CustomHeader(l0), Self::CustomHeader(r0)) => l0 == r0,
             _ => false,
         }
@@ -277,11 +456,68 @@ impl PartialEq for HeaderKey {
 }
 impl std::cmp::Eq for HeaderKey {}
 impl HeaderKey {
+    /// Fails unless the name is an RFC 9110 token.
     pub fn new(value: String) -> Result<HeaderKey, ParseHttpError> {
-        Ok(HeaderKey::from_str(&value).unwrap())
+        match HeaderKey::from_str(&value) {
+            std::result::Result::Ok(key) => Ok(key),
+            std::result::Result::Err(e) => Err(e),
+        }
+    }
+
+    pub fn to_cased_string(&self, case: HeaderCase) -> String {
+        let mut result = Vec::new();
+        // Writing to a Vec cannot fail
+        let _ = self.write_cased(&mut result, case);
+        String::from_utf8_lossy(&result).into_owned()
+    }
+
+    /// Writes the name spelled according to `case`.
+    pub fn write_cased(&self, w: &mut impl Write, case: HeaderCase) -> io::Result<()> {
+        match (self, case) {
+            (HeaderKey::StandardHeader(_, Some(original)), HeaderCase::Original) => {
+                w.write_all(original.as_bytes())
+            }
+            (HeaderKey::StandardHeader(standard_header, _), HeaderCase::Lowercase) => {
+                write_mapped(w, standard_header.as_str(), |_, byte| byte.to_ascii_lowercase())
+            }
+            (HeaderKey::StandardHeader(standard_header, _), _) => {
+                w.write_all(standard_header.as_str().as_bytes())
+            }
+            (HeaderKey::CustomHeader(custom_header), HeaderCase::Original) => {
+                w.write_all(custom_header.0.as_bytes())
+            }
+            (HeaderKey::CustomHeader(custom_header), HeaderCase::Lowercase) => {
+                write_mapped(w, &custom_header.0, |_, byte| byte.to_ascii_lowercase())
+            }
+            (HeaderKey::CustomHeader(custom_header), HeaderCase::TitleCase) => {
+                write_mapped(w, &custom_header.0, |previous, byte| match previous {
+                    None | Some(b'-') => byte.to_ascii_uppercase(),
+                    _ => byte.to_ascii_lowercase(),
+                })
+            }
+        }
     }
 }
 
+/// Writes `name` with each byte passed through `map`, which also sees the
+/// preceding unmapped byte. Goes through a stack buffer to avoid allocating.
+fn write_mapped(
+    w: &mut impl Write,
+    name: &str,
+    mut map: impl FnMut(Option<u8>, u8) -> u8,
+) -> io::Result<()> {
+    let mut buffer = [0u8; 64];
+    let mut previous = None;
+    for piece in name.as_bytes().chunks(buffer.len()) {
+        for (slot, &byte) in buffer.iter_mut().zip(piece) {
+            *slot = map(previous, byte);
+            previous = Some(byte);
+        }
+        w.write_all(&buffer[..piece.len()])?;
+    }
+    std::result::Result::Ok(())
+}
+
 pub struct HeaderValue(String);
 impl Clone for HeaderValue {
     fn clone(&self) -> Self {
@@ -300,13 +536,13 @@ impl Into<Vec<u8>> for &HeaderValue {
 }
 impl Into<Result<HeaderValue, ParseHttpError>> for String {
     fn into(self) -> Result<HeaderValue, ParseHttpError> {
-        Ok(HeaderValue { 0: self })
+        HeaderValue::new(self)
     }
 }
 impl Into<Result<HeaderValue, ParseHttpError>> for Vec<u8> {
     fn into(self) -> Result<HeaderValue, ParseHttpError> {
         match String::from_utf8(self) {
-            std::result::Result::Ok(value) => Ok(HeaderValue { 0: value }),
+            std::result::Result::Ok(value) => HeaderValue::new(value),
             std::result::Result::Err(_e) => Err(ParseHttpError::ParseHeaderError(
                 "Invalid utf-8 Value".to_string(),
             )),
@@ -319,9 +555,28 @@ impl Into<Result<HeaderValue, ParseHttpError>> for &[u8] {
     }
 }
 impl HeaderValue {
+    /// Fails unless the value matches the RFC 9110 field-value grammar:
+    /// no control characters other than HTAB, and no leading or trailing
+    /// whitespace. This keeps CR and LF from splitting the message.
     pub fn new(value: String) -> Result<HeaderValue, ParseHttpError> {
+        if !is_field_value(value.as_bytes()) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid header value: {:?}",
+                value
+            )));
+        }
         Ok(HeaderValue { 0: value })
     }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+pub(crate) fn is_field_value(value: &[u8]) -> bool {
+    let is_field_char = |b: &u8| *b == b'\t' || (*b >= b' ' && *b != 0x7f);
+    let is_whitespace = |b: Option<&u8>| matches!(b, Some(b' ' | b'\t'));
+    value.iter().all(is_field_char) && !is_whitespace(value.first()) && !is_whitespace(value.last())
 }
 
 pub struct Header {
@@ -377,8 +632,11 @@ impl Into<Result<Header, ParseHttpError>> for Vec<u8> {
             std::result::Result::Err(e) => return Err(e),
         };
 
+        // Strip the optional whitespace around the field value
         let value = Into::<Result<HeaderValue, ParseHttpError>>::into(
-            data_str[separate_index + 1..].to_string(),
+            data_str[separate_index + 1..]
+                .trim_matches(|c| c == ' ' || c == '\t')
+                .to_string(),
         )?;
 
         Ok(Header { key, value })
@@ -399,7 +657,7 @@ impl Header {
 }
 
 pub struct FormDataSection {
-    pub headers: HashMap<HeaderKey, HeaderValue>,
+    pub headers: HeaderMap,
     pub data: Vec<u8>,
 }
 impl Clone for FormDataSection{
@@ -416,20 +674,19 @@ impl Into<Result<Vec<u8>, ParseHttpError>> for FormDataSection {
 impl Into<Result<Vec<u8>, ParseHttpError>> for &FormDataSection {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
         let mut result = Vec::new();
-        for (key, value) in &self.headers {
-            result.append(&mut Into::<Vec<u8>>::into(key));
-            // result.append(&mut " ".as_bytes().to_vec());
-            result.append(&mut Into::<Vec<u8>>::into(value));
-            // result.append(&mut "\r\n".as_bytes().to_vec());
-        }
-        result.append(&mut "\r\n\r\n".as_bytes().to_vec());
-        result.append(&mut self.data.clone());
+        io_result(self.write_to(&mut result))?;
         Ok(result)
     }
 }
 impl FormDataSection {
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        self.headers.write_to(w, HeaderCase::Original)?;
+        w.write_all(b"\r\n")?;
+        w.write_all(&self.data)
+    }
+
     pub fn new(
-        headers: HashMap<HeaderKey, HeaderValue>,
+        headers: HeaderMap,
         data: Vec<u8>,
     ) -> Result<FormDataSection, ParseHttpError> {
         Ok(Self {
@@ -437,6 +694,58 @@ impl FormDataSection {
             headers: headers,
         })
     }
+
+    /// The parsed Content-Disposition header, if present.
+    pub fn content_disposition(&self) -> Option<Result<ContentDisposition, ParseHttpError>> {
+        self.headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Disposition))
+            .map(|value| ContentDisposition::parse(value.as_str()))
+    }
+
+    /// The form field name from Content-Disposition.
+    pub fn name(&self) -> Option<String> {
+        match self.content_disposition()? {
+            Ok(disposition) => disposition.name().map(|name| name.to_string()),
+            Err(_) => None,
+        }
+    }
+
+    /// The uploaded file name from Content-Disposition, decoding
+    /// `filename*` when present.
+    pub fn filename(&self) -> Option<String> {
+        match self.content_disposition()? {
+            Ok(disposition) => disposition.filename(),
+            Err(_) => None,
+        }
+    }
+
+    /// The declared Content-Transfer-Encoding, `7bit` when absent.
+    pub fn content_transfer_encoding(&self) -> Result<ContentTransferEncoding, ParseHttpError> {
+        match self
+            .headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Transfer_Encoding))
+        {
+            Some(value) => ContentTransferEncoding::parse(value.as_str()),
+            None => Ok(ContentTransferEncoding::SevenBit),
+        }
+    }
+
+    /// The section data with its Content-Transfer-Encoding undone.
+    pub fn decoded_data(&self) -> Result<Vec<u8>, ParseHttpError> {
+        self.content_transfer_encoding()?.decode(&self.data)
+    }
+
+    /// The section's Content-Type. RFC 7578 section 4.4 makes `text/plain`
+    /// the default when the header is absent.
+    pub fn content_type(&self) -> Result<MediaType, ParseHttpError> {
+        match self
+            .headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Type))
+        {
+            Some(value) => MediaType::parse(value.as_str()),
+            None => MediaType::new("text", "plain"),
+        }
+    }
 }
 
 pub struct FormData {
@@ -450,65 +759,36 @@ impl Clone for FormData{
 }
 impl FormData {
     pub fn parse(boundary: String, raw_data: Vec<u8>) -> Result<FormData, ParseHttpError> {
-        let boundary_marker = format!("--{}\r\n", boundary);
-        let boundary_marker_bytes = boundary_marker.as_bytes();
-
-        let mut parts = Vec::new();
-        let mut start = 0;
-        while let Some(pos) = raw_data[start..]
-            .windows(boundary_marker.len())
-            .position(|chunk| chunk == boundary_marker_bytes)
-        {
-            parts.push(&raw_data[start..start + pos]);
-            start += pos + boundary_marker.len();
-        }
-        parts.push(&raw_data[start..]); // Add the remaining part
-
-        let mut formdata_sections: Vec<FormDataSection> = Vec::new();
+        let mut parser = MultipartParser::new(&boundary);
+        let events = parser.feed(&raw_data)?;
+        parser.finish()?;
 
-        for part in parts {
-            if part.is_empty() || part == b"--" {
-                continue; // Skip empty or terminating boundary
-            }
-            let body_separator = "\r\n\r\n".as_bytes();
-            let sections = part
-                .windows(body_separator.len())
-                .position(|chunk| chunk == body_separator)
-                .unwrap_or(part.len());
-
-            let header_section = &part[0..sections];
-            let body_section = &part[(sections + 4)..(part.len() - 2)];
-
-            let mut headers: HashMap<HeaderKey, HeaderValue> = HashMap::new();
-            for line in header_section.split(|&b| b == b'\n') {
-                let colon_index =
-                    line.iter()
-                        .position(|&b| b == b':')
-                        .ok_or(ParseHttpError::ParseHeaderError(
-                            "Invalid formdata header".to_string(),
-                        ));
-                match colon_index {
-                    std::result::Result::Ok(colon_index) => {
-                        let (key, value) = line.split_at(colon_index);
-                        headers.insert(
-                            Into::<Result<HeaderKey, ParseHttpError>>::into(key)?,
-                            Into::<Result<HeaderValue, ParseHttpError>>::into(value)?,
-                        );
+        let mut sections: Vec<FormDataSection> = Vec::new();
+        for event in events {
+            match event {
+                MultipartEvent::PartStart(headers) => sections.push(FormDataSection {
+                    headers,
+                    data: Vec::new(),
+                }),
+                MultipartEvent::PartData(data) => {
+                    if let Some(section) = sections.last_mut() {
+                        section.data.extend_from_slice(&data);
                     }
-                    std::result::Result::Err(e) => return Err(e),
                 }
+                _ => {}
             }
-
-            formdata_sections.push(FormDataSection {
-                headers,
-                data: body_section.to_vec(),
-            });
         }
 
-        Ok(FormData {
-            boundary,
-            sections: formdata_sections,
-        })
+        Ok(FormData { boundary, sections })
+    }
+
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        for section in &self.sections {
+            write!(w, "--{}\r\n", self.boundary)?;
+            section.write_to(w)?;
+            w.write_all(b"\r\n")?;
+        }
+        write!(w, "--{}--", self.boundary)
     }
 
     pub fn encode(&self) -> Result<String, ParseHttpError> {
@@ -537,15 +817,7 @@ impl Into<Result<Vec<u8>, ParseHttpError>> for FormData {
 impl Into<Result<Vec<u8>, ParseHttpError>> for &FormData {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
         let mut result = Vec::new();
-        for section in &self.sections {
-            result.append(&mut "--".as_bytes().to_vec());
-            result.append(&mut self.boundary.clone().as_bytes().to_vec());
-            result.append(&mut "\r\n".as_bytes().to_vec());
-            result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(section)?.clone());
-            result.append(&mut "\r\n".as_bytes().to_vec());
-        }
-        result.splice(result.len() - 2.., b"--".to_vec());
-
+        io_result(self.write_to(&mut result))?;
         Ok(result)
     }
 }
@@ -554,83 +826,282 @@ impl Into<Result<FormData, ParseHttpError>> for Vec<u8> {
         let Some(firstline_end) = self.windows(2).position(|chunk| chunk == b"\r\n") else {
             return Err(ParseHttpError::FormdataBoundaryNotFound);
         };
+        if firstline_end < 2 {
+            return Err(ParseHttpError::FormdataBoundaryNotFound);
+        }
 
         let boundary = self[2..firstline_end].to_vec();
         let boundary = Into::<Result<String, ParseHttpError>>::into(String::from_utf8(boundary))?;
+        FormData::parse(boundary, self)
+    }
+}
+impl FormData {
+    pub fn new(
+        sections: Vec<FormDataSection>,
+        boundary: String,
+    ) -> Result<FormData, ParseHttpError> {
+        Ok(FormData { boundary, sections })
+    }
 
-        let boundary_marker = format!("--{}\r\n", boundary);
-        let boundary_marker_bytes = boundary_marker.as_bytes();
+    /// The `multipart/form-data` media type carrying this form's boundary.
+    pub fn content_type(&self) -> Result<MediaType, ParseHttpError> {
+        let mut media_type = MediaType::new("multipart", "form-data")?;
+        media_type.set_param("boundary", &self.boundary)?;
+        Ok(media_type)
+    }
 
-        let mut parts = Vec::new();
-        let mut start = 0;
-        while let Some(pos) = self[start..]
-            .windows(boundary_marker.len())
-            .position(|chunk| chunk == boundary_marker_bytes)
-        {
-            parts.push(&self[start..start + pos]);
-            start += pos + boundary_marker.len();
+    /// Whether the delimiter line appears in any section, which would
+    /// make the boundary unusable.
+    fn boundary_collides(&self, boundary: &str) -> bool {
+        let delimiter = format!("--{}", boundary);
+        self.sections.iter().any(|section| {
+            section
+                .data
+                .windows(delimiter.len())
+                .any(|window| window == delimiter.as_bytes())
+                || section
+                    .headers
+                    .iter()
+                    .any(|(_, value)| value.as_str().contains(&delimiter))
+        })
+    }
+}
+
+impl TryFrom<FormData> for Multipart {
+    type Error = ParseHttpError;
+
+    fn try_from(form_data: FormData) -> std::result::Result<Self, Self::Error> {
+        let media_type = match form_data.content_type() {
+            Ok(media_type) => media_type,
+            Err(error) => return std::result::Result::Err(error),
+        };
+        let parts = form_data
+            .sections
+            .into_iter()
+            .map(|section| BodyPart::new(section.headers, section.data))
+            .collect();
+        std::result::Result::Ok(Multipart {
+            media_type,
+            preamble: Vec::new(),
+            parts,
+            epilogue: Vec::new(),
+        })
+    }
+}
+
+pub struct FormDataBuilder {
+    boundary: Option<String>,
+    sections: Vec<FormDataSection>,
+}
+impl Clone for FormDataBuilder {
+    fn clone(&self) -> Self {
+        Self { boundary: self.boundary.clone(), sections: self.sections.clone() }
+    }
+}
+impl FormDataBuilder {
+    pub fn new() -> Result<FormDataBuilder, ParseHttpError> {
+        Ok(FormDataBuilder {
+            boundary: None,
+            sections: Vec::new(),
+        })
+    }
+}
+impl Result<FormDataBuilder, ParseHttpError> {
+    /// Uses a fixed boundary instead of generating one.
+    pub fn set_boundary(&mut self, boundary: &str) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        match self {
+            Ok(this) => match check_boundary(boundary) {
+                Ok(()) => {
+                    this.boundary = Some(boundary.to_string());
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
         }
-        parts.push(&self[start..]); // Add the remaining part
+    }
+
+    pub fn add_text(&mut self, name: &str, value: &str) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        self.add_field(name, None, None, None, value.as_bytes().to_vec())
+    }
 
-        let mut formdata_sections: Vec<FormDataSection> = Vec::new();
+    /// Adds a text field written with the given transfer encoding.
+    pub fn add_encoded_text(
+        &mut self,
+        name: &str,
+        value: &str,
+        encoding: ContentTransferEncoding,
+    ) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        self.add_field(name, None, None, Some(encoding), value.as_bytes().to_vec())
+    }
+
+    pub fn add_file(
+        &mut self,
+        name: &str,
+        filename: &str,
+        content_type: MediaType,
+        data: Vec<u8>,
+    ) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        self.add_field(name, Some(filename), Some(content_type), None, data)
+    }
 
-        for part in parts {
-            if part.is_empty() || part == b"--" {
-                continue; // Skip empty or terminating boundary
+    /// Adds a file part written with the given transfer encoding.
+    pub fn add_encoded_file(
+        &mut self,
+        name: &str,
+        filename: &str,
+        content_type: MediaType,
+        data: Vec<u8>,
+        encoding: ContentTransferEncoding,
+    ) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        self.add_field(name, Some(filename), Some(content_type), Some(encoding), data)
+    }
+
+    pub fn add_section(
+        &mut self,
+        section: FormDataSection,
+    ) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        match self {
+            Ok(this) => {
+                this.sections.push(section);
+                self
             }
-            let body_separator = "\r\n\r\n".as_bytes();
-            let sections = part
-                .windows(body_separator.len())
-                .position(|chunk| chunk == body_separator)
-                .unwrap_or(part.len());
-
-            let header_section = &part[0..sections];
-            let body_section = &part[(sections + 4)..(part.len() - 2)];
-
-            let mut headers: HashMap<HeaderKey, HeaderValue> = HashMap::new();
-            for line in header_section.split(|&b| b == b'\n') {
-                let colon_index =
-                    line.iter()
-                        .position(|&b| b == b':')
-                        .ok_or(ParseHttpError::ParseHeaderError(
-                            "Invalid formdata header".to_string(),
-                        ));
-                match colon_index {
-                    std::result::Result::Ok(colon_index) => {
-                        let (key, value) = line.split_at(colon_index);
-                        headers.insert(
-                            Into::<Result<HeaderKey, ParseHttpError>>::into(key)?,
-                            Into::<Result<HeaderValue, ParseHttpError>>::into(value)?,
-                        );
-                    }
-                    std::result::Result::Err(e) => return Err(e),
-                }
+            Err(_) => self,
+        }
+    }
+
+    fn add_field(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<MediaType>,
+        encoding: Option<ContentTransferEncoding>,
+        data: Vec<u8>,
+    ) -> &mut Result<FormDataBuilder, ParseHttpError> {
+        let headers = match section_headers(name, filename, content_type.as_ref(), encoding.as_ref()) {
+            Ok(headers) => headers,
+            Err(error) => {
+                *self = Err(error);
+                return self;
             }
+        };
+        let data = match encoding {
+            Some(encoding) => encoding.encode(&data),
+            None => data,
+        };
+        self.add_section(FormDataSection { headers, data })
+    }
 
-            formdata_sections.push(FormDataSection {
-                headers,
-                data: body_section.to_vec(),
-            });
+    /// Builds the form, generating a boundary that does not occur in any
+    /// section when none was set.
+    pub fn build(self) -> Result<FormData, ParseHttpError> {
+        let this = self?;
+        let mut form = FormData {
+            boundary: String::new(),
+            sections: this.sections,
+        };
+        match this.boundary {
+            Some(boundary) => {
+                if form.boundary_collides(&boundary) {
+                    return Err(ParseHttpError::ParseFormDataError(format!(
+                        "Boundary occurs in form content: {}",
+                        boundary
+                    )));
+                }
+                form.boundary = boundary;
+            }
+            None => loop {
+                let boundary = generate_boundary();
+                if !form.boundary_collides(&boundary) {
+                    form.boundary = boundary;
+                    break;
+                }
+            },
         }
+        Ok(form)
+    }
+}
 
-        Ok(FormData {
-            boundary,
-            sections: formdata_sections,
-        })
+/// The headers of a built section. Names, filenames and media types come
+/// from the caller, so their values are validated like any other header.
+fn section_headers(
+    name: &str,
+    filename: Option<&str>,
+    content_type: Option<&MediaType>,
+    encoding: Option<&ContentTransferEncoding>,
+) -> Result<HeaderMap, ParseHttpError> {
+    let mut disposition = ContentDisposition::form_data(name)?;
+    if let Some(filename) = filename {
+        disposition.set_param("filename", filename)?;
+    }
+    let mut headers = HeaderMap::new();
+    headers.append(
+        HeaderKey::from(StandardHeaders::Content_Disposition),
+        HeaderValue::new(disposition.to_string())?,
+    );
+    if let Some(content_type) = content_type {
+        headers.append(
+            HeaderKey::from(StandardHeaders::Content_Type),
+            HeaderValue::new(content_type.to_string())?,
+        );
+    }
+    if let Some(encoding) = encoding {
+        headers.append(
+            HeaderKey::from(StandardHeaders::Content_Transfer_Encoding),
+            HeaderValue::new(encoding.to_string())?,
+        );
     }
+    Ok(headers)
 }
-impl FormData {
-    pub fn new(
-        sections: Vec<FormDataSection>,
-        boundary: String,
-    ) -> Result<FormData, ParseHttpError> {
-        Ok(FormData { boundary, sections })
+
+/// Checks the RFC 2046 section 5.1.1 grammar: 1 to 70 bchars, not ending
+/// in a space. Anything else could end the delimiter line early.
+pub(crate) fn check_boundary(boundary: &str) -> Result<(), ParseHttpError> {
+    let is_bchar = |b: u8| b.is_ascii_alphanumeric() || b"'()+_,-./:=? ".contains(&b);
+    if boundary.is_empty()
+        || boundary.len() > 70
+        || boundary.ends_with(' ')
+        || !boundary.bytes().all(is_bchar)
+    {
+        return Err(ParseHttpError::ParseFormDataError(format!(
+            "Invalid boundary: {:?}",
+            boundary
+        )));
     }
+    Ok(())
+}
+
+/// A random boundary. The standard library has no random number generator,
+/// so the randomly keyed hasher behind `HashMap` is used as the source.
+pub(crate) fn generate_boundary() -> String {
+    format!("----FormBoundary{}", random_hex())
+}
+
+/// 128 bits from the randomly keyed std hasher, as 32 hex digits.
+pub(crate) fn random_hex() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut result = String::with_capacity(32);
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        result.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    result
 }
 
 pub enum Body {
     Data(Vec<u8>),
     FormData(FormData),
+    UrlEncoded(UrlEncoded),
+    Multipart(Multipart),
+    Chunked(ChunkedBody),
     None
 }
 impl Clone for Body{
@@ -638,6 +1109,9 @@ impl Clone for Body{
         match self {
             Self::Data(arg0) => Self::Data(arg0.clone()),
             Self::FormData(arg0) => Self::FormData(arg0.clone()),
+            Self::UrlEncoded(arg0) => Self::UrlEncoded(arg0.clone()),
+            Self::Multipart(arg0) => Self::Multipart(arg0.clone()),
+            Self::Chunked(arg0) => Self::Chunked(arg0.clone()),
             Self::None => Self::None,
         }
     }
@@ -650,11 +1124,9 @@ impl Into<Result<Vec<u8>, ParseHttpError>> for Body {
 }
 impl Into<Result<Vec<u8>, ParseHttpError>> for &Body {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
-        match self {
-            Body::Data(data) => Ok(data.clone()),
-            Body::FormData(form_data) => Into::<Result<Vec<u8>, ParseHttpError>>::into(form_data),
-            Body::None => Ok(Vec::new()),
-        }
+        let mut result = Vec::new();
+        self.write_to(&mut result)?;
+        Ok(result)
     }
 }
 impl Into<Body> for String {
@@ -663,19 +1135,97 @@ impl Into<Body> for String {
     }
 }
 impl Body{
+    /// Writes the body as it appears on the wire, chunked framing included.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        match self {
+            Body::Data(data) => io_result(w.write_all(data)),
+            Body::FormData(form_data) => io_result(form_data.write_to(w)),
+            Body::UrlEncoded(form) => io_result(write!(w, "{}", form)),
+            Body::Multipart(multipart) => multipart.write_to(w),
+            Body::Chunked(chunked) => io_result(chunked.write_to(w)),
+            Body::None => Ok(()),
+        }
+    }
+
     pub fn as_formdata(self) -> Result<FormData, ParseHttpError> {
         match self {
             Body::Data(vec) => Into::<Result<FormData, ParseHttpError>>::into(vec) ,
             Body::FormData(form_data) => Ok(form_data),
+            Body::Chunked(chunked) => Into::<Result<FormData, ParseHttpError>>::into(chunked.data()),
+            Body::UrlEncoded(_) => Err(ParseHttpError::ParseFormDataError(
+                "Urlencoded Body".to_string(),
+            )),
+            Body::Multipart(multipart) => {
+                let data = multipart.encode()?;
+                FormData::parse(multipart.boundary().to_string(), data)
+            }
             Body::None => Err(ParseHttpError::ParseFormDataError("Empty Body".to_string())),
         }
     }
+
+    pub fn as_urlencoded(self) -> Result<UrlEncoded, ParseHttpError> {
+        match self {
+            Body::Data(vec) => UrlEncoded::parse(&vec),
+            Body::UrlEncoded(form) => Ok(form),
+            Body::Chunked(chunked) => UrlEncoded::parse(&chunked.data()),
+            Body::FormData(_) | Body::Multipart(_) => Err(ParseHttpError::ParseBodyError(
+                "Multipart Body".to_string(),
+            )),
+            Body::None => Ok(UrlEncoded::new()),
+        }
+    }
+
+    /// Parses the body as a multipart body described by `media_type`.
+    pub fn as_multipart(self, media_type: MediaType) -> Result<Multipart, ParseHttpError> {
+        match self {
+            Body::Multipart(multipart) => Ok(multipart),
+            Body::FormData(form_data) => Multipart::try_from(form_data).into(),
+            body => {
+                let data = Into::<Result<Vec<u8>, ParseHttpError>>::into(&body)?;
+                Multipart::parse(media_type, &data)
+            }
+        }
+    }
+
+    /// Decodes a raw body into the representation its Content-Type names.
+    /// Bodies of other media types are returned unchanged.
+    pub fn decode_as(self, content_type: &str) -> Result<Body, ParseHttpError> {
+        if is_urlencoded(content_type) {
+            return Ok(Body::UrlEncoded(self.as_urlencoded()?));
+        }
+        if let Ok(media_type) = MediaType::parse(content_type) {
+            if media_type.is("multipart", "form-data") {
+                let Some(boundary) = media_type.boundary() else {
+                    return Err(ParseHttpError::FormdataBoundaryNotFound);
+                };
+                let data = Into::<Result<Vec<u8>, ParseHttpError>>::into(&self)?;
+                return Ok(Body::FormData(FormData::parse(boundary.to_string(), data)?));
+            }
+            if media_type.type_() == "multipart" {
+                return Ok(Body::Multipart(self.as_multipart(media_type)?));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Re-encodes the body as chunks of at most `chunk_size` bytes.
+    pub fn to_chunked(&self, chunk_size: usize) -> Result<ChunkedBody, ParseHttpError> {
+        match self {
+            Body::Chunked(chunked) => Ok(chunked.clone()),
+            _ => {
+                let data = Into::<Result<Vec<u8>, ParseHttpError>>::into(self)?;
+                Ok(ChunkedBody::from_data(&data, chunk_size))
+            }
+        }
+    }
 }
 
 
 #[cfg(test)]
 mod test_formdata {
-    use super::FormData;
+    use super::{FormData, FormDataBuilder};
+    use crate::http::content_transfer_encoding::ContentTransferEncoding;
+    use crate::http::media_type::MediaType;
 
     #[test]
     fn form_data_test_1() {
@@ -694,4 +1244,97 @@ mod test_formdata {
             crate::Result::Err(error) => panic!("{}", error),
         }
     }
+
+    #[test]
+    fn form_data_builder_test() {
+        let mut builder = FormDataBuilder::new();
+        builder
+            .add_text("title", "a \"quoted\" title")
+            .add_file(
+                "upload",
+                "notes.txt",
+                MediaType::new("text", "plain").unwrap(),
+                b"line one\r\nline two".to_vec(),
+            );
+        let form = builder.build().unwrap();
+        assert!(form.boundary.len() <= 70);
+
+        let encoded = form.encode().unwrap();
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\na \"quoted\" title\r\n--{b}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"notes.txt\"\r\nContent-Type: text/plain\r\n\r\nline one\r\nline two\r\n--{b}--",
+            b = form.boundary
+        );
+        assert_eq!(encoded, expected);
+
+        let parsed = FormData::parse(form.boundary.clone(), encoded.into_bytes()).unwrap();
+        assert_eq!(parsed.sections[1].filename(), Some("notes.txt".to_string()));
+        assert_eq!(parsed.sections[1].data, b"line one\r\nline two");
+
+        let mut builder = FormDataBuilder::new();
+        builder.set_boundary("abc").add_text("field", "--abc");
+        assert!(matches!(builder.build(), crate::Result::Err(_)));
+        for boundary in ["x\r\nX-Evil: 1", "trailing ", "", &"b".repeat(71)] {
+            let mut builder = FormDataBuilder::new();
+            builder.set_boundary(boundary).add_text("field", "value");
+            assert!(matches!(builder.build(), crate::Result::Err(_)));
+        }
+        let mut builder = FormDataBuilder::new();
+        builder.set_boundary("a'(b)+_,-./:=? c").add_text("field", "value");
+        assert!(matches!(builder.build(), crate::Result::Ok(_)));
+
+        let mut builder = FormDataBuilder::new();
+        builder.add_text("a\r\nX-Injected: 1", "v");
+        assert!(matches!(builder.build(), crate::Result::Err(_)));
+        let mut builder = FormDataBuilder::new();
+        builder.add_file(
+            "upload",
+            "a.txt\r\nX-Injected: 1",
+            MediaType::new("text", "plain").unwrap(),
+            Vec::new(),
+        );
+        assert!(matches!(builder.build(), crate::Result::Err(_)));
+    }
+
+    #[test]
+    fn form_data_transfer_encoding_test() {
+        let mut builder = FormDataBuilder::new();
+        builder
+            .set_boundary("b")
+            .add_encoded_text("note", "na\u{ef}ve", ContentTransferEncoding::QuotedPrintable)
+            .add_encoded_file(
+                "blob",
+                "blob.bin",
+                MediaType::new("application", "octet-stream").unwrap(),
+                vec![0, 255, 10],
+                ContentTransferEncoding::Base64,
+            );
+        let form = builder.build().unwrap();
+        let encoded = form.encode().unwrap();
+        assert!(encoded.contains("Content-Transfer-Encoding: quoted-printable\r\n\r\nna=C3=AFve\r\n"));
+        assert!(encoded.contains("Content-Transfer-Encoding: base64\r\n\r\nAP8K\r\n"));
+
+        let parsed = FormData::parse("b".to_string(), encoded.into_bytes()).unwrap();
+        assert_eq!(parsed.sections[0].decoded_data().unwrap(), "na\u{ef}ve".as_bytes());
+        assert_eq!(parsed.sections[1].data, b"AP8K");
+        assert_eq!(parsed.sections[1].decoded_data().unwrap(), vec![0, 255, 10]);
+    }
+
+    #[test]
+    fn form_data_section_disposition_test() {
+        let data = "--b\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"; filename*=UTF-8''%C3%A4.txt\r\nContent-Type: image/png\r\n\r\n\x01\x02\r\n--b--\r\nepilogue";
+        let form = FormData::parse("b".to_string(), data.as_bytes().to_vec()).unwrap();
+        assert_eq!(form.sections.len(), 2);
+
+        let title = &form.sections[0];
+        assert_eq!(title.name(), Some("title".to_string()));
+        assert_eq!(title.filename(), None);
+        assert_eq!(title.content_type().unwrap().essence(), "text/plain");
+        assert_eq!(title.data, b"hello");
+
+        let file = &form.sections[1];
+        assert_eq!(file.name(), Some("file".to_string()));
+        assert_eq!(file.filename(), Some("\u{e4}.txt".to_string()));
+        assert!(file.content_type().unwrap().is("image", "png"));
+        assert_eq!(file.data, b"\x01\x02");
+    }
 }
@@ -1,9 +1,28 @@
 use crate::Result::{self, Err, Ok};
-use std::{clone, collections::hash_map::HashMap, ops::Deref, result, str::FromStr, vec};
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::{clone, ops::Deref, result, str::FromStr, vec};
 
-use super::http::{Body, FormData, Header, HeaderKey, HeaderValue, ParseHttpError};
+use super::chunked::ChunkedBody;
+use super::header_map::HeaderMap;
+use super::http::{
+    check_boundary, io_result, Body, FormData, Header, HeaderCase, HeaderKey, HeaderValue, ParseHttpError,
+    StandardHeaders,
+};
+use super::media_type::{is_token, MediaType};
+use super::multipart::Multipart;
+use super::parser::{validate_request_head, HttpRequestParser, HttpResponseParser, Status};
+use super::status::StatusCode;
+use super::typed_header::TypedHeader;
+use super::uri::{RequestTarget, TargetForm};
+use super::urlencoded::{is_urlencoded, UrlEncoded, URLENCODED_MEDIA_TYPE};
+use super::version::Version;
 
-pub const VERSION: &str = "HTTP/1.1";
+/// The version used when building messages.
+pub const VERSION: Version = Version::Http11;
+/// The request method. Methods outside the built-in set are kept as
+/// `Extension` with their exact, case-sensitive token (RFC 9110 section 9.1).
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub enum RequestMethod {
     CONNECT,
     DELETE,
@@ -14,6 +33,7 @@ pub enum RequestMethod {
     POST,
     PUT,
     TRACE,
+    Extension(String),
 }
 impl Clone for RequestMethod {
     fn clone(&self) -> Self {
@@ -27,29 +47,34 @@ impl Clone for RequestMethod {
             Self::POST => Self::POST,
             Self::PUT => Self::PUT,
             Self::TRACE => Self::TRACE,
+            Self::Extension(arg0) => Self::Extension(arg0.clone()),
         }
     }
 }
 impl Into<Result<Vec<u8>, ParseHttpError>> for RequestMethod {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
         match self {
-            RequestMethod::CONNECT => Ok("CONNECT".as_bytes().to_vec()),
-            RequestMethod::DELETE => Ok("DELETE".as_bytes().to_vec()),
-            RequestMethod::GET => Ok("GET".as_bytes().to_vec()),
-            RequestMethod::HEAD => Ok("HEAD".as_bytes().to_vec()),
-            RequestMethod::OPTIONS => Ok("OPTIONS".as_bytes().to_vec()),
-            RequestMethod::PATCH => Ok("PATCH".as_bytes().to_vec()),
-            RequestMethod::POST => Ok("POST".as_bytes().to_vec()),
-            RequestMethod::PUT => Ok("PUT".as_bytes().to_vec()),
-            RequestMethod::TRACE => Ok("TRACE".as_bytes().to_vec()),
-            _ => Err(ParseHttpError::InvalidHttpMethod),
+            RequestMethod::Extension(method) if !is_token(&method) => {
+                Err(ParseHttpError::InvalidHttpMethod)
+            }
+            method => Ok(method.as_str().as_bytes().to_vec()),
         }
     }
 }
 impl Into<Result<RequestMethod, ParseHttpError>> for Vec<u8> {
     fn into(self) -> Result<RequestMethod, ParseHttpError> {
         let data_str = Into::<Result<String, ParseHttpError>>::into(String::from_utf8(self))?;
-        match data_str.as_str() {
+        RequestMethod::from_token(&data_str)
+    }
+}
+impl Display for RequestMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl RequestMethod {
+    pub fn from_token(token: &str) -> Result<RequestMethod, ParseHttpError> {
+        match token {
             "CONNECT" => Ok(RequestMethod::CONNECT),
             "DELETE" => Ok(RequestMethod::DELETE),
             "GET" => Ok(RequestMethod::GET),
@@ -59,61 +84,152 @@ impl Into<Result<RequestMethod, ParseHttpError>> for Vec<u8> {
             "POST" => Ok(RequestMethod::POST),
             "PUT" => Ok(RequestMethod::PUT),
             "TRACE" => Ok(RequestMethod::TRACE),
+            token if is_token(token) => Ok(RequestMethod::Extension(token.to_string())),
             _ => Err(ParseHttpError::InvalidHttpMethod),
         }
     }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RequestMethod::CONNECT => "CONNECT",
+            RequestMethod::DELETE => "DELETE",
+            RequestMethod::GET => "GET",
+            RequestMethod::HEAD => "HEAD",
+            RequestMethod::OPTIONS => "OPTIONS",
+            RequestMethod::PATCH => "PATCH",
+            RequestMethod::POST => "POST",
+            RequestMethod::PUT => "PUT",
+            RequestMethod::TRACE => "TRACE",
+            RequestMethod::Extension(method) => method,
+        }
+    }
+
+    /// Safe methods are read-only (RFC 9110 section 9.2.1). Extension
+    /// methods use their IANA registration; unknown ones are not safe.
+    pub fn is_safe(&self) -> bool {
+        match self {
+            RequestMethod::GET
+            | RequestMethod::HEAD
+            | RequestMethod::OPTIONS
+            | RequestMethod::TRACE => true,
+            RequestMethod::Extension(method) => {
+                extension_properties(method).is_some_and(|(safe, _)| safe)
+            }
+            _ => false,
+        }
+    }
+
+    /// Idempotent methods may be retried automatically (RFC 9110 section 9.2.2).
+    pub fn is_idempotent(&self) -> bool {
+        match self {
+            RequestMethod::PUT | RequestMethod::DELETE => true,
+            RequestMethod::Extension(method) => {
+                extension_properties(method).is_some_and(|(_, idempotent)| idempotent)
+            }
+            method => method.is_safe(),
+        }
+    }
+
+    /// Whether responses to the method may be stored by a cache
+    /// (RFC 9110 section 9.2.3). POST responses need explicit freshness.
+    pub fn is_cacheable(&self) -> bool {
+        match self {
+            RequestMethod::GET | RequestMethod::HEAD | RequestMethod::POST => true,
+            RequestMethod::Extension(method) => method == "QUERY",
+            _ => false,
+        }
+    }
+}
+
+/// `(safe, idempotent)` for methods in the IANA HTTP Method Registry.
+fn extension_properties(method: &str) -> Option<(bool, bool)> {
+    match method {
+        "PRI" | "PROPFIND" | "QUERY" | "REPORT" | "SEARCH" => Some((true, true)),
+        "ACL" | "BASELINE-CONTROL" | "BIND" | "CHECKIN" | "CHECKOUT" | "COPY" | "LABEL"
+        | "LINK" | "MERGE" | "MKACTIVITY" | "MKCALENDAR" | "MKCOL" | "MKREDIRECTREF"
+        | "MKWORKSPACE" | "MOVE" | "ORDERPATCH" | "PROPPATCH" | "REBIND" | "UNBIND"
+        | "UNCHECKOUT" | "UNLINK" | "UNLOCK" | "UPDATE" | "UPDATEREDIRECTREF"
+        | "VERSION-CONTROL" => Some((false, true)),
+        "LOCK" => Some((false, false)),
+        _ => None,
+    }
 }
 
 pub struct RequestStartLine {
     pub method: RequestMethod,
-    pub path: String,
-    version: String,
+    pub target: RequestTarget,
+    pub version: Version,
 }
 impl Clone for RequestStartLine{
     fn clone(&self) -> Self {
-        Self { method: self.method.clone(), path: self.path.clone(), version: self.version.clone() }
+        Self { method: self.method.clone(), target: self.target.clone(), version: self.version }
     }
 }
 impl Clone for ResponseStartLine{
     fn clone(&self) -> Self {
-        Self { version: self.version.clone(), response_code: self.response_code.clone(), response_msg: self.response_msg.clone() }
+        Self { version: self.version, response_code: self.response_code, response_msg: self.response_msg.clone() }
     }
 }
 impl Into<Result<Vec<u8>, ParseHttpError>> for RequestStartLine {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
         let mut result = Vec::new();
-        result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(
-            self.method,
-        )?);
-        result.append(&mut " ".as_bytes().to_vec());
-        result.append(&mut self.path.as_bytes().to_vec());
-        result.append(&mut " ".as_bytes().to_vec());
-        result.append(&mut VERSION.as_bytes().to_vec());
-
+        self.write_to(&mut result)?;
         Ok(result)
     }
 }
+impl RequestStartLine {
+    /// Writes the start line without its trailing CRLF.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        if let RequestMethod::Extension(method) = &self.method {
+            if !is_token(method) {
+                return Err(ParseHttpError::InvalidHttpMethod);
+            }
+        }
+        io_result(write!(w, "{} {} {}", self.method, self.target, self.version))
+    }
+}
 impl Into<Result<RequestStartLine, ParseHttpError>> for Vec<u8> {
     fn into(self) -> Result<RequestStartLine, ParseHttpError> {
         let splitted = self.split(|(&i)| i == b' ').collect::<Vec<&[u8]>>();
         if splitted.len() != 3 {
             return Err(ParseHttpError::InvalidHttp);
         }
+        let method = Into::<Result<RequestMethod, ParseHttpError>>::into(splitted[0].to_vec())?;
+        let target = RequestTarget::parse(&Into::<Result<String, ParseHttpError>>::into(
+            String::from_utf8(splitted[1].to_vec()),
+        )?)?;
+        validate_target_form(&method, &target)?;
         Ok(RequestStartLine {
-            method: Into::<Result<RequestMethod, ParseHttpError>>::into(splitted[0].to_vec())?,
-            path: Into::<Result<String, ParseHttpError>>::into(String::from_utf8(
-                splitted[1].to_vec(),
-            ))?,
-            version: Into::<Result<String, ParseHttpError>>::into(String::from_utf8(
-                splitted[2].to_vec(),
-            ))?,
+            method,
+            target,
+            version: Version::parse(splitted[2])?,
         })
     }
 }
 
+/// CONNECT takes an authority-form target and only OPTIONS may use `*`
+/// (RFC 9112 sections 3.2.3 and 3.2.4).
+pub(crate) fn validate_target_form(
+    method: &RequestMethod,
+    target: &RequestTarget,
+) -> Result<(), ParseHttpError> {
+    let valid = match target.form() {
+        TargetForm::Authority => matches!(method, RequestMethod::CONNECT),
+        TargetForm::Asterisk => matches!(method, RequestMethod::OPTIONS),
+        TargetForm::Origin | TargetForm::Absolute => !matches!(method, RequestMethod::CONNECT),
+    };
+    if !valid {
+        return Err(ParseHttpError::ParseError(format!(
+            "Request target {} is not allowed for this method",
+            target
+        )));
+    }
+    Ok(())
+}
+
 pub struct HttpRequest {
     pub start_line: RequestStartLine,
-    pub headers: HashMap<HeaderKey, HeaderValue>,
+    pub headers: HeaderMap,
     pub body: Body,
 }
 impl Clone for HttpRequest{
@@ -121,70 +237,168 @@ impl Clone for HttpRequest{
         Self { start_line: self.start_line.clone(), headers: self.headers.clone(), body: self.body.clone() }
     }
 }
+impl HttpRequest {
+    /// Whether the connection stays open after this request.
+    pub fn keep_alive(&self) -> bool {
+        self.start_line.version.keep_alive(&self.headers)
+    }
+
+    /// The header parsed as `H`, or `None` when it is absent.
+    pub fn typed_get<H: TypedHeader>(&self) -> Option<Result<H, ParseHttpError>> {
+        self.headers.typed_get()
+    }
+
+    /// Sets the header, replacing any values it had.
+    pub fn typed_insert<H: TypedHeader>(&mut self, header: H) -> Result<(), ParseHttpError> {
+        self.headers.typed_insert(header)
+    }
+
+    /// The decoded query string pairs of the request target.
+    pub fn query_pairs(&self) -> Result<Vec<(String, String)>, ParseHttpError> {
+        self.start_line.target.query_pairs()
+    }
+
+    /// The body decoded as `application/x-www-form-urlencoded`, failing if
+    /// the Content-Type header names another media type.
+    pub fn urlencoded(&self) -> Result<UrlEncoded, ParseHttpError> {
+        match self.headers.get(&HeaderKey::from(StandardHeaders::Content_Type)) {
+            Some(content_type) if is_urlencoded(content_type.as_str()) => {
+                self.body.clone().as_urlencoded()
+            }
+            Some(content_type) => Err(ParseHttpError::ParseBodyError(format!(
+                "Unexpected Content-Type: {}",
+                content_type.as_str()
+            ))),
+            None => Err(ParseHttpError::ParseBodyError(
+                "Missing Content-Type".to_string(),
+            )),
+        }
+    }
+
+    /// The parsed Content-Type header, if present.
+    pub fn content_type(&self) -> Option<Result<MediaType, ParseHttpError>> {
+        self.headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Type))
+            .map(|value| MediaType::parse(value.as_str()))
+    }
+
+    /// The body parsed as `multipart/form-data`, using the boundary given in
+    /// the request's own Content-Type header.
+    pub fn form_data(&self) -> Result<FormData, ParseHttpError> {
+        let media_type = match self.content_type() {
+            Some(media_type) => media_type?,
+            None => {
+                return Err(ParseHttpError::ParseFormDataError(
+                    "Missing Content-Type".to_string(),
+                ))
+            }
+        };
+        if !media_type.is("multipart", "form-data") {
+            return Err(ParseHttpError::ParseFormDataError(format!(
+                "Unexpected Content-Type: {}",
+                media_type.essence()
+            )));
+        }
+        let Some(boundary) = media_type.boundary() else {
+            return Err(ParseHttpError::FormdataBoundaryNotFound);
+        };
+        check_boundary(boundary)?;
+        match &self.body {
+            Body::FormData(form_data) => Ok(form_data.clone()),
+            body => FormData::parse(
+                boundary.to_string(),
+                Into::<Result<Vec<u8>, ParseHttpError>>::into(body)?,
+            ),
+        }
+    }
+
+    /// The body parsed as a `multipart/*` body of any subtype, using the
+    /// boundary from the Content-Type header.
+    pub fn multipart(&self) -> Result<Multipart, ParseHttpError> {
+        match self.content_type() {
+            Some(media_type) => self.body.clone().as_multipart(media_type?),
+            None => Err(ParseHttpError::ParseBodyError(
+                "Missing Content-Type".to_string(),
+            )),
+        }
+    }
+
+    /// Replaces a raw body with the representation named by Content-Type.
+    pub fn decode_body(&mut self) -> Result<(), ParseHttpError> {
+        let Some(content_type) = self
+            .headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Type))
+        else {
+            return Ok(());
+        };
+        let body = std::mem::replace(&mut self.body, Body::None);
+        self.body = body.decode_as(content_type.as_str())?;
+        Ok(())
+    }
+}
 impl Into<Result<Vec<u8>, ParseHttpError>> for HttpRequest {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
+        self.encode_with_case(HeaderCase::Original)
+    }
+}
+impl HttpRequest {
+    /// Serializes the message, spelling header names according to `case`.
+    pub fn encode_with_case(&self, case: HeaderCase) -> Result<Vec<u8>, ParseHttpError> {
         let mut result = Vec::new();
+        self.write_with_case(&mut result, case)?;
+        Ok(result)
+    }
 
-        result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(
-            self.start_line,
-        )?);
-        result.append(&mut "\r\n".as_bytes().to_vec());
+    /// Writes the message to `w` without consuming it.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        self.write_with_case(w, HeaderCase::Original)
+    }
 
-        for (key, value) in self.headers {
-            result.append(&mut key.into());
-            result.append(&mut ": ".as_bytes().to_vec());
-            result.append(&mut value.into());
-            result.append(&mut "\r\n".as_bytes().to_vec());
+    /// Appends the message to `buf`, so one buffer can be reused across
+    /// messages. On failure `buf` is left as it was.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), ParseHttpError> {
+        let start = buf.len();
+        let result = self.write_to(buf);
+        if let Err(_) = result {
+            buf.truncate(start);
         }
-        result.append(&mut "\r\n".as_bytes().to_vec());
-
-        result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(
-            self.body,
-        )?);
+        result
+    }
 
-        Ok(result)
+    /// Writes the message to `w`, spelling header names according to `case`.
+    pub fn write_with_case(&self, w: &mut impl Write, case: HeaderCase) -> Result<(), ParseHttpError> {
+        self.start_line.write_to(w)?;
+        io_result(w.write_all(b"\r\n"))?;
+        io_result(self.headers.write_to(w, case))?;
+        io_result(w.write_all(b"\r\n"))?;
+        self.body.write_to(w)
     }
 }
 impl Into<Result<HttpRequest, ParseHttpError>> for Vec<u8> {
     fn into(self) -> Result<HttpRequest, ParseHttpError> {
-        let mut start = 0;
-        let mut lines = Vec::new();
-        while let Some(pos) = self[start..]
-            .windows(2)
-            .position(|line_break| line_break == b"\r\n")
-        {
-            lines.push(&self[start..start + pos]);
-            start += pos + 2;
-        }
-        let mut lines = lines.iter().peekable();
-
-        let start_line = match lines.next() {
-            Some(line) => Into::<Result<RequestStartLine, ParseHttpError>>::into(line.to_vec())?,
-            None => return Err(ParseHttpError::InvalidHttp),
-        };
-
-        let mut headers = HashMap::new();
-        while let Some(line) = lines.peek() {
-            if line.is_empty() {
-                lines.next();
-                break;
+        match HttpRequestParser::new().feed(&self)? {
+            Status::Complete(message, consumed) => {
+                check_trailing_data(consumed, self.len())?;
+                Ok(message)
             }
-            let header = Into::<Result<Header, ParseHttpError>>::into(line.to_vec())?;
-            headers.insert(header.key, header.value);
-            lines.next();
+            Status::Partial => Err(ParseHttpError::IncompleteMessage),
         }
+    }
+}
 
-        let body = lines.flat_map(|line| line.to_vec()).collect();
-
-        Ok(HttpRequest {
-            start_line,
-            headers,
-            body: Body::Data(body),
-        })
+/// The conversions from bytes expect exactly one message. Anything after it,
+/// such as a pipelined or smuggled request, is an error rather than dropped.
+fn check_trailing_data(consumed: usize, len: usize) -> Result<(), ParseHttpError> {
+    if consumed < len {
+        return Err(ParseHttpError::ParseError(format!(
+            "{} bytes after the end of the message",
+            len - consumed
+        )));
     }
+    Ok(())
 }
 
-struct HttpRequestBuilder {
+pub struct HttpRequestBuilder {
     context: HttpRequest,
 }
 impl Clone for HttpRequestBuilder{
@@ -199,39 +413,97 @@ impl HttpRequestBuilder {
 }
 impl Result<HttpRequestBuilder, ParseHttpError> {
     fn new(method: RequestMethod, path: String) -> Result<HttpRequestBuilder, ParseHttpError> {
+        let target = RequestTarget::parse(&path)?;
+        validate_target_form(&method, &target)?;
         Ok(HttpRequestBuilder {
             context: HttpRequest {
                 start_line: RequestStartLine {
                     method: method,
-                    path: path,
-                    version: VERSION.to_string(),
+                    target,
+                    version: VERSION,
                 },
-                headers: HashMap::new(),
+                headers: HeaderMap::new(),
                 body: Body::None,
             },
         })
     }
 
+    pub fn set_version(&mut self, version: Version) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
+        match self {
+            Ok(this) if matches!(this.context.body, Body::Chunked(_)) && !version.supports_chunked() => {
+                *self = Err(chunked_unsupported(version));
+                self
+            }
+            Ok(this) => {
+                this.context.start_line.version = version;
+                self
+            }
+            Err(_) => self,
+        }
+    }
+
     pub fn add_header(
         &mut self,
         header: Header,
     ) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
         match self {
             Ok(this) => {
-                this.context.headers.insert(header.key, header.value);
+                this.context.headers.append(header.key, header.value);
                 self
             }
             Err(error) => self,
         }
     }
 
+    pub fn add_query(
+        &mut self,
+        key: &str,
+        value: &str,
+    ) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
+        match self {
+            Ok(this) => match this.context.start_line.target.append_query_pair(key, value) {
+                Ok(()) => self,
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
+        }
+    }
+
+    pub fn add_urlencoded(
+        &mut self,
+        form: UrlEncoded,
+    ) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
+        match self {
+            Ok(this) => match set_urlencoded_headers(&mut this.context.headers, &form) {
+                Ok(()) => {
+                    this.context.body = Body::UrlEncoded(form);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
+        }
+    }
+
     pub fn add_body(&mut self, data: Vec<u8>) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
         match self {
-            Ok(this) => {
-                this.context.body = Body::Data(data);
-                self
-            }
-            Err(error) => self,
+            Ok(this) => match set_data_headers(&mut this.context.headers, &data) {
+                Ok(()) => {
+                    this.context.body = Body::Data(data);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
         }
     }
 
@@ -240,55 +512,149 @@ impl Result<HttpRequestBuilder, ParseHttpError> {
         formdata: FormData,
     ) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
         match self {
-            Ok(this) => {
-                this.context.body = Body::FormData(formdata);
+            Ok(this) => match set_formdata_headers(&mut this.context.headers, &formdata) {
+                Ok(()) => {
+                    this.context.body = Body::FormData(formdata);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
+        }
+    }
+
+    pub fn add_chunked(
+        &mut self,
+        chunked: ChunkedBody,
+    ) -> &mut Result<HttpRequestBuilder, ParseHttpError> {
+        match self {
+            Ok(this) if !this.context.start_line.version.supports_chunked() => {
+                *self = Err(chunked_unsupported(this.context.start_line.version));
                 self
             }
-            Err(error) => self,
+            Ok(this) => match HeaderValue::new("chunked".to_string()) {
+                Ok(value) => {
+                    this.context
+                        .headers
+                        .remove(&HeaderKey::from(StandardHeaders::Content_Length));
+                    this.context.headers.insert(
+                        HeaderKey::from(StandardHeaders::Transfer_Encoding),
+                        value,
+                    );
+                    this.context.body = Body::Chunked(chunked);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
         }
     }
 
+    /// Fails for a request the parser would reject for its Host headers,
+    /// such as an HTTP/1.1 request without one.
     pub fn build(self) -> Result<HttpRequest, ParseHttpError> {
-        match self {
-            Ok(this) => Ok(this.context),
-            Err(error) => Err(ParseHttpError::InvalidHttp),
-        }
+        let this = self?;
+        validate_request_head(&this.context.start_line, &this.context.headers)?;
+        Ok(this.context)
     }
 }
 
+fn chunked_unsupported(version: Version) -> ParseHttpError {
+    ParseHttpError::ParseError(format!("Chunked transfer coding is not available in {}", version))
+}
+
+/// Sets Content-Type with the form's boundary and the Content-Length of the
+/// serialized form, replacing any chunked framing.
+fn set_formdata_headers(headers: &mut HeaderMap, formdata: &FormData) -> Result<(), ParseHttpError> {
+    let content_type = HeaderValue::new(formdata.content_type()?.to_string())?;
+    let length = Into::<Result<Vec<u8>, ParseHttpError>>::into(formdata)?.len();
+    let content_length = HeaderValue::new(length.to_string())?;
+    headers.remove(&HeaderKey::from(StandardHeaders::Transfer_Encoding));
+    headers.insert(HeaderKey::from(StandardHeaders::Content_Type), content_type);
+    headers.insert(HeaderKey::from(StandardHeaders::Content_Length), content_length);
+    Ok(())
+}
+
+/// Sets the Content-Length of raw body data, replacing any length or
+/// chunked framing set for an earlier body.
+fn set_data_headers(headers: &mut HeaderMap, data: &[u8]) -> Result<(), ParseHttpError> {
+    let content_length = HeaderValue::new(data.len().to_string())?;
+    headers.remove(&HeaderKey::from(StandardHeaders::Transfer_Encoding));
+    headers.insert(HeaderKey::from(StandardHeaders::Content_Length), content_length);
+    Ok(())
+}
+
+/// Sets the urlencoded Content-Type and the Content-Length of the encoded
+/// form, replacing any chunked framing.
+fn set_urlencoded_headers(headers: &mut HeaderMap, form: &UrlEncoded) -> Result<(), ParseHttpError> {
+    let content_type = HeaderValue::new(URLENCODED_MEDIA_TYPE.to_string())?;
+    let content_length = HeaderValue::new(form.encode().len().to_string())?;
+    headers.remove(&HeaderKey::from(StandardHeaders::Transfer_Encoding));
+    headers.insert(HeaderKey::from(StandardHeaders::Content_Type), content_type);
+    headers.insert(HeaderKey::from(StandardHeaders::Content_Length), content_length);
+    Ok(())
+}
+
 pub struct ResponseStartLine {
-    pub version: String,
-    pub response_code: u32,
+    pub version: Version,
+    pub response_code: StatusCode,
     pub response_msg: String,
 }
 impl Into<Result<Vec<u8>, ParseHttpError>> for ResponseStartLine {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
         let mut result = Vec::new();
-        result.append(&mut self.version.as_bytes().to_vec());
-        result.append(&mut " ".as_bytes().to_vec());
-        result.append(&mut self.response_code.to_string().as_bytes().to_vec());
-        result.append(&mut " ".as_bytes().to_vec());
-        result.append(&mut self.response_msg.as_bytes().to_vec());
-        // result.append(&mut "\r\n".as_bytes().to_vec());
-
+        self.write_to(&mut result)?;
         Ok(result)
     }
 }
+impl ResponseStartLine {
+    /// Writes the start line without its trailing CRLF. Fails for a reason
+    /// phrase that would split the response, see `check_reason_phrase`.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        check_reason_phrase(&self.response_msg)?;
+        io_result(write!(
+            w,
+            "{} {} {}",
+            self.version, self.response_code, self.response_msg
+        ))
+    }
+}
+/// `reason-phrase = 1*( HTAB / SP / VCHAR / obs-text )` (RFC 9112 section
+/// 4). CR, LF and the other controls would end the status line early.
+fn check_reason_phrase(reason: &str) -> Result<(), ParseHttpError> {
+    if reason.bytes().any(|b| (b < b' ' && b != b'\t') || b == 0x7f) {
+        return Err(ParseHttpError::ParseError(format!(
+            "Invalid reason phrase: {:?}",
+            reason
+        )));
+    }
+    Ok(())
+}
+
 impl Into<Result<ResponseStartLine, ParseHttpError>> for Vec<u8> {
+    /// Parses `HTTP-version SP status-code SP [ reason-phrase ]`. The reason
+    /// phrase may be empty, and the space before it missing (RFC 9112 4).
     fn into(self) -> Result<ResponseStartLine, ParseHttpError> {
-        let splitted = self.split(|(&i)| i == b' ').collect::<Vec<&[u8]>>();
-        if splitted.len() < 3 {
+        let Some(version_end) = self.iter().position(|&b| b == b' ') else {
             return Err(ParseHttpError::InvalidHttp);
-        }
-        let version =
-            Into::<Result<String, ParseHttpError>>::into(String::from_utf8(splitted[0].to_vec()))?;
-        let response_code =
-            Into::<Result<String, ParseHttpError>>::into(String::from_utf8(splitted[1].to_vec()))?
-                .parse::<u32>()
-                .unwrap();
-        let response_msg = Into::<Result<String, ParseHttpError>>::into(String::from_utf8(
-            splitted[2..].join(&b' '),
-        ))?;
+        };
+        let version = Version::parse(&self[..version_end])?;
+
+        let rest = &self[version_end + 1..];
+        let code_end = rest.iter().position(|&b| b == b' ').unwrap_or(rest.len());
+        let response_code = StatusCode::parse(&rest[..code_end])?;
+
+        // The reason phrase may carry obs-text, which is not valid utf-8
+        let response_msg = match rest.get(code_end + 1..) {
+            Some(reason) => String::from_utf8_lossy(reason).into_owned(),
+            None => String::new(),
+        };
 
         Ok(ResponseStartLine {
             version,
@@ -300,7 +666,7 @@ impl Into<Result<ResponseStartLine, ParseHttpError>> for Vec<u8> {
 
 pub struct HttpResponse {
     pub start_line: ResponseStartLine,
-    pub headers: HashMap<HeaderKey, HeaderValue>,
+    pub headers: HeaderMap,
     pub body: Body,
 }
 impl Clone for HttpResponse {
@@ -310,64 +676,89 @@ impl Clone for HttpResponse {
 }
 impl Into<Result<Vec<u8>, ParseHttpError>> for HttpResponse {
     fn into(self) -> Result<Vec<u8>, ParseHttpError> {
-        let mut result = Vec::new();
+        self.encode_with_case(HeaderCase::Original)
+    }
+}
+impl HttpResponse {
+    /// Whether the connection stays open after this response.
+    pub fn keep_alive(&self) -> bool {
+        self.start_line.version.keep_alive(&self.headers)
+    }
 
-        result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(
-            self.start_line,
-        )?);
-        result.append(&mut "\r\n".as_bytes().to_vec());
+    /// The header parsed as `H`, or `None` when it is absent.
+    pub fn typed_get<H: TypedHeader>(&self) -> Option<Result<H, ParseHttpError>> {
+        self.headers.typed_get()
+    }
 
-        for (key, value) in self.headers {
-            result.append(&mut key.into());
-            result.append(&mut ": ".as_bytes().to_vec());
-            result.append(&mut value.into());
-            result.append(&mut "\r\n".as_bytes().to_vec());
-        }
-        result.append(&mut "\r\n".as_bytes().to_vec());
+    /// Sets the header, replacing any values it had.
+    pub fn typed_insert<H: TypedHeader>(&mut self, header: H) -> Result<(), ParseHttpError> {
+        self.headers.typed_insert(header)
+    }
 
-        result.append(&mut Into::<Result<Vec<u8>, ParseHttpError>>::into(
-            self.body,
-        )?);
+    /// The parsed Content-Type header, if present.
+    pub fn content_type(&self) -> Option<Result<MediaType, ParseHttpError>> {
+        self.headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Type))
+            .map(|value| MediaType::parse(value.as_str()))
+    }
+
+    /// The body parsed as a `multipart/*` body of any subtype, using the
+    /// boundary from the Content-Type header.
+    pub fn multipart(&self) -> Result<Multipart, ParseHttpError> {
+        match self.content_type() {
+            Some(media_type) => self.body.clone().as_multipart(media_type?),
+            None => Err(ParseHttpError::ParseBodyError(
+                "Missing Content-Type".to_string(),
+            )),
+        }
+    }
 
+    /// Serializes the message, spelling header names according to `case`.
+    pub fn encode_with_case(&self, case: HeaderCase) -> Result<Vec<u8>, ParseHttpError> {
+        let mut result = Vec::new();
+        self.write_with_case(&mut result, case)?;
         Ok(result)
     }
+
+    /// Writes the message to `w` without consuming it.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        self.write_with_case(w, HeaderCase::Original)
+    }
+
+    /// Appends the message to `buf`, so one buffer can be reused across
+    /// messages. On failure `buf` is left as it was.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> Result<(), ParseHttpError> {
+        let start = buf.len();
+        let result = self.write_to(buf);
+        if let Err(_) = result {
+            buf.truncate(start);
+        }
+        result
+    }
+
+    /// Writes the message to `w`, spelling header names according to `case`.
+    pub fn write_with_case(&self, w: &mut impl Write, case: HeaderCase) -> Result<(), ParseHttpError> {
+        self.start_line.write_to(w)?;
+        io_result(w.write_all(b"\r\n"))?;
+        io_result(self.headers.write_to(w, case))?;
+        io_result(w.write_all(b"\r\n"))?;
+        self.body.write_to(w)
+    }
 }
 impl Into<Result<HttpResponse, ParseHttpError>> for Vec<u8> {
     fn into(self) -> Result<HttpResponse, ParseHttpError> {
-        let mut start = 0;
-        let mut lines = Vec::new();
-        while let Some(pos) = self[start..]
-            .windows(2)
-            .position(|line_break| line_break == b"\r\n")
-        {
-            lines.push(&self[start..start + pos]);
-            start += pos + 2;
-        }
-        let mut lines = lines.iter().peekable();
-
-        let start_line = match lines.next() {
-            Some(line) => Into::<Result<ResponseStartLine, ParseHttpError>>::into(line.to_vec())?,
-            None => return Err(ParseHttpError::InvalidHttp),
-        };
-
-        let mut headers = HashMap::new();
-        while let Some(line) = lines.peek() {
-            if line.is_empty() {
-                lines.next();
-                break;
+        let mut parser = HttpResponseParser::new();
+        match parser.feed(&self)? {
+            Status::Complete(message, consumed) => {
+                check_trailing_data(consumed, self.len())?;
+                Ok(message)
             }
-            let header = Into::<Result<Header, ParseHttpError>>::into(line.to_vec())?;
-            headers.insert(header.key, header.value);
-            lines.next();
+            // The end of the buffer closes a response without explicit framing
+            Status::Partial => match parser.finish()? {
+                Some(message) => Ok(message),
+                None => Err(ParseHttpError::IncompleteMessage),
+            },
         }
-
-        let body = lines.flat_map(|line| line.to_vec()).collect();
-
-        Ok(HttpResponse {
-            start_line,
-            headers,
-            body: Body::Data(body),
-        })
     }
 }
 
@@ -381,37 +772,57 @@ impl Clone for HttpResponseBuilder {
 }
 impl HttpResponseBuilder {
     pub fn new(
-        response_code: u32,
+        response_code: StatusCode,
         response_msg: String,
     ) -> Result<HttpResponseBuilder, ParseHttpError> {
         Result::<HttpResponseBuilder, ParseHttpError>::new(response_code, response_msg)
     }
 }
 impl Result<HttpResponseBuilder, ParseHttpError> {
+    /// An empty `response_msg` is replaced by the code's canonical reason.
     fn new(
-        response_code: u32,
+        response_code: StatusCode,
         response_msg: String,
     ) -> Result<HttpResponseBuilder, ParseHttpError> {
+        check_reason_phrase(&response_msg)?;
+        let response_msg = match response_code.canonical_reason() {
+            Some(reason) if response_msg.is_empty() => reason.to_string(),
+            _ => response_msg,
+        };
         Ok(HttpResponseBuilder {
             context: HttpResponse {
                 start_line: ResponseStartLine {
-                    version: VERSION.to_string(),
+                    version: VERSION,
                     response_code: response_code,
                     response_msg: response_msg,
                 },
-                headers: HashMap::new(),
+                headers: HeaderMap::new(),
                 body: Body::None,
             },
         })
     }
 
+    pub fn set_version(&mut self, version: Version) -> &mut Result<HttpResponseBuilder, ParseHttpError> {
+        match self {
+            Ok(this) if matches!(this.context.body, Body::Chunked(_)) && !version.supports_chunked() => {
+                *self = Err(chunked_unsupported(version));
+                self
+            }
+            Ok(this) => {
+                this.context.start_line.version = version;
+                self
+            }
+            Err(_) => self,
+        }
+    }
+
     pub fn add_header(
         &mut self,
         header: Header,
     ) -> &mut Result<HttpResponseBuilder, ParseHttpError> {
         match self {
             Ok(this) => {
-                this.context.headers.insert(header.key, header.value);
+                this.context.headers.append(header.key, header.value);
                 self
             }
             Err(error) => self,
@@ -420,11 +831,17 @@ impl Result<HttpResponseBuilder, ParseHttpError> {
 
     pub fn add_body(&mut self, data: Vec<u8>) -> &mut Result<HttpResponseBuilder, ParseHttpError> {
         match self {
-            Ok(this) => {
-                this.context.body = Body::Data(data);
-                self
-            }
-            Err(error) => self,
+            Ok(this) => match set_data_headers(&mut this.context.headers, &data) {
+                Ok(()) => {
+                    this.context.body = Body::Data(data);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
         }
     }
 
@@ -433,18 +850,54 @@ impl Result<HttpResponseBuilder, ParseHttpError> {
         formdata: FormData,
     ) -> &mut Result<HttpResponseBuilder, ParseHttpError> {
         match self {
-            Ok(this) => {
-                this.context.body = Body::FormData(formdata);
+            Ok(this) => match set_formdata_headers(&mut this.context.headers, &formdata) {
+                Ok(()) => {
+                    this.context.body = Body::FormData(formdata);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
+        }
+    }
+
+    pub fn add_chunked(
+        &mut self,
+        chunked: ChunkedBody,
+    ) -> &mut Result<HttpResponseBuilder, ParseHttpError> {
+        match self {
+            Ok(this) if !this.context.start_line.version.supports_chunked() => {
+                *self = Err(chunked_unsupported(this.context.start_line.version));
                 self
             }
-            Err(error) => self,
+            Ok(this) => match HeaderValue::new("chunked".to_string()) {
+                Ok(value) => {
+                    this.context
+                        .headers
+                        .remove(&HeaderKey::from(StandardHeaders::Content_Length));
+                    this.context.headers.insert(
+                        HeaderKey::from(StandardHeaders::Transfer_Encoding),
+                        value,
+                    );
+                    this.context.body = Body::Chunked(chunked);
+                    self
+                }
+                Err(error) => {
+                    *self = Err(error);
+                    self
+                }
+            },
+            Err(_) => self,
         }
     }
 
     pub fn build(self) -> Result<HttpResponse, ParseHttpError> {
         match self {
             Ok(this) => Ok(this.context),
-            Err(error) => Err(ParseHttpError::InvalidHttp),
+            Err(error) => Err(error),
         }
     }
 }
@@ -452,10 +905,20 @@ impl Result<HttpResponseBuilder, ParseHttpError> {
 #[cfg(test)]
 mod test_http {
     use super::{Body, HttpRequest, RequestMethod, RequestStartLine, VERSION};
-    use crate::http::http::{Header, HeaderKey, HeaderValue, ParseHttpError};
-    use crate::http::http_message::{HttpRequestBuilder, HttpResponse, ResponseStartLine};
+    use crate::http::chunked::ChunkedBody;
+    use crate::http::status::StatusCode;
+    use crate::http::uri::RequestTarget;
+    use crate::http::version::Version;
+    use crate::http::http::{
+        FormDataBuilder, Header, HeaderCase, HeaderKey, HeaderValue, ParseHttpError, StandardHeaders,
+    };
+    use crate::http::http_message::{
+        HttpRequestBuilder, HttpResponse, HttpResponseBuilder, ResponseStartLine,
+    };
     use crate::Result;
-    use std::collections::HashMap;
+    use crate::http::header_map::HeaderMap;
+    use crate::http::date::HttpDate;
+    use crate::http::typed_header::{Connection, ContentLength, Date, Host, Upgrade};
 
     #[test]
     fn http_request_test() {
@@ -463,12 +926,12 @@ mod test_http {
         let request = HttpRequest {
             start_line: RequestStartLine {
                 method: RequestMethod::GET,
-                path: String::from("/index.html"),
-                version: String::from(VERSION),
+                target: RequestTarget::parse("/index.html").unwrap(),
+                version: VERSION,
             },
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert(
+                let mut headers = HeaderMap::new();
+                headers.append(
                     HeaderKey::new("Host".to_string()).unwrap(),
                     HeaderValue::new("example.com".to_string()).unwrap(),
                 );
@@ -482,12 +945,12 @@ mod test_http {
         assert_eq!(String::from_utf8(bytes).unwrap(), expected);
 
         // Test request parsing
-        let input = b"GET /test.html HTTP/1.1\r\nContent-Type: text/html\r\n\r\nHello World";
+        let input = b"GET /test.html HTTP/1.1\r\nHost: example.com\r\nContent-Type: text/html\r\nContent-Length: 11\r\n\r\nHello World";
         let request: HttpRequest =
             Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
 
         assert!(matches!(request.start_line.method, RequestMethod::GET));
-        assert_eq!(request.start_line.path, "/test.html");
+        assert_eq!(request.start_line.target, "/test.html");
         // assert_eq!(
         //     request
         //         .headers
@@ -505,7 +968,7 @@ mod test_http {
     #[should_panic]
     fn invalid_request_test() {
         // Test invalid request parsing
-        let input = b"INVALID /test.html HTTP/1.1\r\n\r\n";
+        let input = b"INV@LID /test.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
         let _: HttpRequest = Into::<Result<HttpRequest, _>>::into(input.to_vec()).unwrap();
     }
 
@@ -514,13 +977,13 @@ mod test_http {
         // Test response serialization
         let response = HttpResponse {
             start_line: ResponseStartLine {
-                version: String::from(VERSION),
-                response_code: 200,
+                version: VERSION,
+                response_code: StatusCode::OK,
                 response_msg: String::from("OK"),
             },
             headers: {
-                let mut headers = HashMap::new();
-                headers.insert(
+                let mut headers = HeaderMap::new();
+                headers.append(
                     HeaderKey::new("Content-Type".to_string()).unwrap(),
                     HeaderValue::new("text/html".to_string()).unwrap(),
                 );
@@ -557,7 +1020,10 @@ mod test_http {
     #[test]
     fn htt_request_builder_test() {
         let mut builder = HttpRequestBuilder::new(RequestMethod::POST, "/submit".to_string());
+        // HTTP/1.1 requests need a Host, as when parsing
+        assert!(matches!(builder.clone().build(), Result::Err(ParseHttpError::ParseHeaderError(_))));
         let mut builder = builder
+            .add_header(Header::new("Host".to_string(), "example.com".to_string()).unwrap())
             .add_header(Header {
                 key: HeaderKey::new("Content-Type".to_string()).unwrap(),
                 value: HeaderValue::new("application/json".to_string()).unwrap(),
@@ -567,7 +1033,7 @@ mod test_http {
         let request = builder.clone().build().unwrap();
 
         assert!(matches!(request.start_line.method, RequestMethod::POST));
-        assert_eq!(request.start_line.path, "/submit");
+        assert_eq!(request.start_line.target, "/submit");
         // assert_eq!(
         //     request
         //         .headers
@@ -580,4 +1046,324 @@ mod test_http {
         //     b"{\"key\":\"value\"}".to_vec()
         // );
     }
+
+    #[test]
+    fn header_case_test() {
+        let input = b"GET / HTTP/1.1\r\nhost: example.com\r\nx-request-id: 42\r\n\r\n";
+        let request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+
+        assert_eq!(
+            request.headers.get(&HeaderKey::from(StandardHeaders::Host)).unwrap().as_str(),
+            "example.com"
+        );
+        assert_eq!(
+            request
+                .headers
+                .get(&HeaderKey::new("X-Request-Id".to_string()).unwrap())
+                .unwrap()
+                .as_str(),
+            "42"
+        );
+
+        let original = request.encode_with_case(HeaderCase::Original).unwrap();
+        assert_eq!(original, input.to_vec());
+        let title = request.encode_with_case(HeaderCase::TitleCase).unwrap();
+        assert_eq!(
+            String::from_utf8(title).unwrap(),
+            "GET / HTTP/1.1\r\nHost: example.com\r\nX-Request-Id: 42\r\n\r\n"
+        );
+        let lower = request.encode_with_case(HeaderCase::Lowercase).unwrap();
+        assert_eq!(lower, input.to_vec());
+    }
+
+    #[test]
+    fn request_query_test() {
+        let mut builder = HttpRequestBuilder::new(RequestMethod::GET, "/search?lang=en".to_string());
+        builder
+            .add_header(Header::new("Host".to_string(), "example.com".to_string()).unwrap())
+            .add_query("q", "rust & http")
+            .add_query("q", "100%");
+        let request = builder.build().unwrap();
+
+        let bytes: Vec<u8> = Into::<Result<Vec<u8>, ParseHttpError>>::into(request.clone()).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "GET /search?lang=en&q=rust+%26+http&q=100%25 HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        );
+        assert_eq!(
+            request.query_pairs().unwrap(),
+            vec![
+                ("lang".to_string(), "en".to_string()),
+                ("q".to_string(), "rust & http".to_string()),
+                ("q".to_string(), "100%".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn urlencoded_body_test() {
+        let input = b"POST /login HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded; charset=utf-8\r\nContent-Length: 23\r\n\r\nuser=alice&pass=a%26b+c";
+        let mut request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+
+        let form = request.urlencoded().unwrap();
+        assert_eq!(form.get("user"), Some("alice"));
+        assert_eq!(form.get("pass"), Some("a&b c"));
+
+        request.decode_body().unwrap();
+        assert!(matches!(request.body, Body::UrlEncoded(ref decoded) if decoded == &form));
+
+        let mut builder = HttpRequestBuilder::new(RequestMethod::POST, "/login".to_string());
+        builder
+            .add_header(Header::new("Host".to_string(), "example.com".to_string()).unwrap())
+            .add_urlencoded(form.clone());
+        let bytes: Vec<u8> =
+            Into::<Result<Vec<u8>, ParseHttpError>>::into(builder.clone().build().unwrap()).unwrap();
+        let expected = "POST /login HTTP/1.1\r\nHost: example.com\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 23\r\n\r\nuser=alice&pass=a%26b+c";
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+        let parsed: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(expected.as_bytes().to_vec()).unwrap();
+        assert_eq!(parsed.urlencoded().unwrap(), form);
+
+        // Replacing a chunked body drops its framing
+        builder
+            .add_chunked(ChunkedBody::from_data(b"data", 2))
+            .add_urlencoded(form);
+        let bytes: Vec<u8> =
+            Into::<Result<Vec<u8>, ParseHttpError>>::into(builder.build().unwrap()).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn request_form_data_test() {
+        let input = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Type: multipart/form-data; boundary=\"x-y z\"\r\nContent-Length: 75\r\n\r\n--x-y z\r\nContent-Disposition: form-data; name=\"field1\"\r\n\r\nvalue1\r\n--x-y z--";
+        let request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+        let form = request.form_data().unwrap();
+        assert_eq!(form.boundary, "x-y z");
+        assert_eq!(form.sections.len(), 1);
+        assert_eq!(form.sections[0].data, b"value1");
+
+        let mut builder = HttpRequestBuilder::new(RequestMethod::POST, "/upload".to_string());
+        builder.add_header(Header::new("Host".to_string(), "example.com".to_string()).unwrap()).add_header(
+            Header::new("Content-Type".to_string(), "multipart/form-data".to_string()).unwrap(),
+        );
+        let request = builder.build().unwrap();
+        assert!(matches!(
+            request.form_data(),
+            crate::Result::Err(ParseHttpError::FormdataBoundaryNotFound)
+        ));
+    }
+
+    #[test]
+    fn request_formdata_builder_test() {
+        let mut form = FormDataBuilder::new();
+        form.set_boundary("xyz").add_text("field", "value");
+        let form = form.build().unwrap();
+
+        let mut builder = HttpRequestBuilder::new(RequestMethod::POST, "/submit".to_string());
+        builder
+            .add_header(Header::new("Host".to_string(), "example.com".to_string()).unwrap())
+            .add_formdata(form);
+        let bytes: Vec<u8> =
+            Into::<Result<Vec<u8>, ParseHttpError>>::into(builder.clone().build().unwrap()).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes.clone()).unwrap(),
+            "POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Type: multipart/form-data; boundary=xyz\r\nContent-Length: 69\r\n\r\n--xyz\r\nContent-Disposition: form-data; name=\"field\"\r\n\r\nvalue\r\n--xyz--"
+        );
+
+        let request: HttpRequest = Into::<Result<HttpRequest, ParseHttpError>>::into(bytes).unwrap();
+        let form = request.form_data().unwrap();
+        assert_eq!(form.sections[0].name(), Some("field".to_string()));
+        assert_eq!(form.sections[0].data, b"value");
+
+        // Replacing the form keeps the framing in step with the new body
+        builder.add_body(b"raw".to_vec());
+        let request = builder.build().unwrap();
+        let length = request.headers.get_all(&HeaderKey::from(StandardHeaders::Content_Length));
+        assert_eq!(length.map(HeaderValue::as_str).collect::<Vec<_>>(), ["3"]);
+    }
+
+    #[test]
+    fn response_byteranges_test() {
+        let input = b"HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=THIS_STRING_SEPARATES\r\n\r\n--THIS_STRING_SEPARATES\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-4/20\r\n\r\nhello\r\n--THIS_STRING_SEPARATES--\r\n";
+        let response: HttpResponse =
+            Into::<Result<HttpResponse, ParseHttpError>>::into(input.to_vec()).unwrap();
+        let multipart = response.multipart().unwrap();
+        assert_eq!(multipart.subtype(), "byteranges");
+        assert_eq!(multipart.parts[0].data(), Some(&b"hello"[..]));
+        assert_eq!(multipart.parts[0].content_range().unwrap().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn response_status_line_test() {
+        let start_line: ResponseStartLine =
+            Into::<Result<ResponseStartLine, ParseHttpError>>::into(b"HTTP/1.1 200".to_vec())
+                .unwrap();
+        assert_eq!(start_line.response_code, StatusCode::OK);
+        assert_eq!(start_line.response_msg, "");
+
+        let start_line: ResponseStartLine =
+            Into::<Result<ResponseStartLine, ParseHttpError>>::into(b"HTTP/1.1 599 ".to_vec())
+                .unwrap();
+        assert!(start_line.response_code.is_server_error());
+        assert_eq!(start_line.response_msg, "");
+
+        for input in [&b"HTTP/1.1 OK"[..], b"HTTP/1.1 2000 OK", b"HTTP/1.1", b" 200 OK"] {
+            let result = Into::<Result<ResponseStartLine, ParseHttpError>>::into(input.to_vec());
+            assert!(matches!(result, Result::Err(_)));
+        }
+
+        let response = HttpResponseBuilder::new(StatusCode::NOT_FOUND, String::new())
+            .build()
+            .unwrap();
+        assert_eq!(response.start_line.response_msg, "Not Found");
+        let builder = HttpResponseBuilder::new(StatusCode::OK, "OK\r\nSet-Cookie: x=1".to_string());
+        assert!(matches!(builder, Result::Err(_)));
+        let start_line = ResponseStartLine {
+            version: VERSION,
+            response_code: StatusCode::OK,
+            response_msg: "OK\r\nSet-Cookie: x=1".to_string(),
+        };
+        assert!(matches!(start_line.write_to(&mut Vec::new()), Result::Err(_)));
+    }
+
+    #[test]
+    fn http_version_test() {
+        let input = b"GET / HTTP/1.0\r\n\r\n";
+        let request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+        assert_eq!(request.start_line.version, Version::Http10);
+        assert!(!request.keep_alive());
+        let bytes: Vec<u8> = Into::<Result<Vec<u8>, ParseHttpError>>::into(request).unwrap();
+        assert_eq!(bytes, input.to_vec());
+
+        let missing_host = b"GET / HTTP/1.1\r\n\r\n";
+        let result = Into::<Result<HttpRequest, ParseHttpError>>::into(missing_host.to_vec());
+        assert!(matches!(result, Result::Err(_)));
+
+        let chunked_10 = b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        let result = Into::<Result<HttpRequest, ParseHttpError>>::into(chunked_10.to_vec());
+        assert!(matches!(result, Result::Err(_)));
+
+        let input = b"HTTP/1.0 200 OK\r\nConnection: keep-alive\r\nContent-Length: 0\r\n\r\n";
+        let response: HttpResponse =
+            Into::<Result<HttpResponse, ParseHttpError>>::into(input.to_vec()).unwrap();
+        assert_eq!(response.start_line.version, Version::Http10);
+        assert!(response.keep_alive());
+
+        let mut builder = HttpRequestBuilder::new(RequestMethod::POST, "/".to_string());
+        builder
+            .set_version(Version::Http10)
+            .add_chunked(ChunkedBody::from_data(b"data", 2));
+        assert!(matches!(builder.build(), Result::Err(ParseHttpError::ParseError(_))));
+
+        let pipelined = b"GET / HTTP/1.1\r\nHost: a\r\n\r\nGET /admin HTTP/1.1\r\nHost: a\r\n\r\n";
+        let result = Into::<Result<HttpRequest, ParseHttpError>>::into(pipelined.to_vec());
+        assert!(matches!(result, Result::Err(_)));
+        let trailing = b"HTTP/1.1 204 No Content\r\n\r\nextra";
+        let result = Into::<Result<HttpResponse, ParseHttpError>>::into(trailing.to_vec());
+        assert!(matches!(result, Result::Err(_)));
+    }
+
+    #[test]
+    fn extension_method_test() {
+        let input = b"PROPFIND /files HTTP/1.1\r\nHost: example.com\r\nDepth: 1\r\n\r\n";
+        let request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+        assert_eq!(
+            request.start_line.method,
+            RequestMethod::Extension("PROPFIND".to_string())
+        );
+        assert!(request.start_line.method.is_safe());
+        let bytes: Vec<u8> = Into::<Result<Vec<u8>, ParseHttpError>>::into(request).unwrap();
+        assert_eq!(bytes, input.to_vec());
+
+        let lock = RequestMethod::from_token("LOCK").unwrap();
+        assert!(!lock.is_safe() && !lock.is_idempotent());
+        let purge = RequestMethod::from_token("PURGE").unwrap();
+        assert!(!purge.is_safe() && !purge.is_idempotent() && !purge.is_cacheable());
+        assert!(RequestMethod::PUT.is_idempotent() && !RequestMethod::PUT.is_safe());
+        assert!(RequestMethod::POST.is_cacheable() && !RequestMethod::POST.is_idempotent());
+        assert!(RequestMethod::from_token("QUERY").unwrap().is_cacheable());
+        assert!(matches!(RequestMethod::from_token("GET /"), Result::Err(_)));
+    }
+
+    #[test]
+    fn write_to_test() {
+        let input = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n5;name=\"a b\"\r\nhello\r\n0\r\nExpires: never\r\n\r\n";
+        let request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+
+        let mut buf = b"prefix".to_vec();
+        request.encode_into(&mut buf).unwrap();
+        request.encode_into(&mut buf).unwrap();
+        assert_eq!(&buf[..6], b"prefix");
+        assert_eq!(&buf[6..6 + input.len()], input);
+        assert_eq!(&buf[6 + input.len()..], input);
+        let encoded = Into::<Result<Vec<u8>, ParseHttpError>>::into(request.clone()).unwrap();
+        assert_eq!(encoded, input.to_vec());
+
+        let mut invalid = request.clone();
+        invalid.start_line.method = RequestMethod::Extension("B@D".to_string());
+        let length = buf.len();
+        assert!(matches!(invalid.encode_into(&mut buf), Result::Err(_)));
+        assert_eq!(buf.len(), length);
+
+        let input = b"HTTP/1.1 404 Not Found\r\nContent-Length: 3\r\n\r\nabc";
+        let response: HttpResponse =
+            Into::<Result<HttpResponse, ParseHttpError>>::into(input.to_vec()).unwrap();
+        let mut out = Vec::new();
+        response.write_to(&mut out).unwrap();
+        assert_eq!(out, input.to_vec());
+    }
+
+    #[test]
+    fn typed_header_test() {
+        let input = b"GET / HTTP/1.1\r\nHost: example.com:8080\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n";
+        let mut request: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()).unwrap();
+        let host = request.typed_get::<Host>().unwrap().unwrap();
+        assert_eq!((host.host.as_str(), host.port), ("example.com", Some(8080)));
+        assert!(request.typed_get::<Connection>().unwrap().unwrap().contains("upgrade"));
+        assert!(request.typed_get::<Upgrade>().unwrap().unwrap().contains("websocket"));
+        assert!(request.typed_get::<ContentLength>().is_none());
+
+        request.typed_insert(Connection::close()).unwrap();
+        assert!(!request.keep_alive());
+
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let mut response: HttpResponse =
+            Into::<Result<HttpResponse, ParseHttpError>>::into(input.to_vec()).unwrap();
+        assert_eq!(response.typed_get::<ContentLength>().unwrap().unwrap().0, 2);
+        response.typed_insert(Date(HttpDate::from_unix_secs(784111777))).unwrap();
+        assert_eq!(
+            response.headers.get(&HeaderKey::from(StandardHeaders::Date)).unwrap().as_str(),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+    }
+
+    #[test]
+    fn header_validation_test() {
+        assert!(matches!(HeaderKey::new("X-Custom".to_string()), Result::Ok(_)));
+        assert!(matches!(HeaderKey::new("X Custom".to_string()), Result::Err(_)));
+        assert!(matches!(HeaderKey::new("X-Custom:".to_string()), Result::Err(_)));
+        assert!(matches!(HeaderKey::new(String::new()), Result::Err(_)));
+
+        assert!(matches!(HeaderValue::new("a\tb \u{e9}".to_string()), Result::Ok(_)));
+        assert!(matches!(HeaderValue::new(String::new()), Result::Ok(_)));
+        assert!(matches!(
+            Header::new("X-Name".to_string(), "a\r\nSet-Cookie: admin=1".to_string()),
+            Result::Err(_)
+        ));
+        assert!(matches!(HeaderValue::new("a\0b".to_string()), Result::Err(_)));
+        assert!(matches!(HeaderValue::new(" padded".to_string()), Result::Err(_)));
+
+        let input = b"GET / HTTP/1.1\r\nHost: example.com\r\nX\"Bad: 1\r\n\r\n";
+        assert!(matches!(
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input.to_vec()),
+            Result::Err(_)
+        ));
+    }
 }
@@ -0,0 +1,274 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::http::ParseHttpError;
+
+/// A parsed media type such as `multipart/form-data; boundary=xyz`
+/// (RFC 9110 section 8.3.1). Type, subtype and parameter names are
+/// lowercased; parameter values keep their case with quoting removed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+impl MediaType {
+    pub fn new(type_: &str, subtype: &str) -> Result<MediaType, ParseHttpError> {
+        if !is_token(type_) || !is_token(subtype) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid media type: {}/{}",
+                type_, subtype
+            )));
+        }
+        Ok(MediaType {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters: Vec::new(),
+        })
+    }
+
+    pub fn parse(value: &str) -> Result<MediaType, ParseHttpError> {
+        let (essence, rest) = match value.find(';') {
+            Some(index) => (&value[..index], &value[index..]),
+            None => (value, ""),
+        };
+        let Some((type_, subtype)) = essence.trim_matches(is_ows).split_once('/') else {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid media type: {}",
+                value
+            )));
+        };
+        let mut media_type = MediaType::new(type_, subtype)?;
+        media_type.parameters = parse_parameters(rest, value)?;
+        Ok(media_type)
+    }
+
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// The `type/subtype` part without parameters.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    pub fn is(&self, type_: &str, subtype: &str) -> bool {
+        self.type_.eq_ignore_ascii_case(type_) && self.subtype.eq_ignore_ascii_case(subtype)
+    }
+
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+
+    /// The value of the first parameter with this name.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets a parameter, replacing any existing value. Fails for values
+    /// that cannot be written, see `check_parameter`.
+    pub fn set_param(&mut self, name: &str, value: &str) -> Result<(), ParseHttpError> {
+        check_parameter(name, value)?;
+        let name = name.to_ascii_lowercase();
+        self.parameters.retain(|(key, _)| key != &name);
+        self.parameters.push((name, value.to_string()));
+        Ok(())
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    pub fn boundary(&self) -> Option<&str> {
+        self.param("boundary")
+    }
+}
+impl Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        write_parameters(f, &self.parameters, false)
+    }
+}
+
+/// Parses a `*( OWS ";" OWS name=value )` parameter list shared by media
+/// types and Content-Disposition. Names are lowercased and quoted values
+/// unescaped; `value` is the whole field, used in error messages.
+pub(crate) fn parse_parameters(
+    mut rest: &str,
+    value: &str,
+) -> Result<Vec<(String, String)>, ParseHttpError> {
+    let mut parameters = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(is_ows);
+        let Some(after) = rest.strip_prefix(';') else {
+            break;
+        };
+        rest = after.trim_start_matches(is_ows);
+        // Empty parameters such as a trailing ';' are tolerated
+        if rest.is_empty() || rest.starts_with(';') {
+            continue;
+        }
+
+        let name_end = rest.find('=').unwrap_or(rest.len());
+        let name = rest[..name_end].trim_end_matches(is_ows);
+        if !is_token(name) || name_end == rest.len() {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid parameter in: {}",
+                value
+            )));
+        }
+        rest = rest[name_end + 1..].trim_start_matches(is_ows);
+
+        let parameter_value = if rest.starts_with('"') {
+            let (quoted, consumed) = parse_quoted_string(rest)?;
+            rest = &rest[consumed..];
+            quoted
+        } else {
+            let end = rest.find(|c| c == ';' || is_ows(c)).unwrap_or(rest.len());
+            let token = &rest[..end];
+            if !is_token(token) {
+                return Err(ParseHttpError::ParseHeaderError(format!(
+                    "Invalid parameter value in: {}",
+                    value
+                )));
+            }
+            rest = &rest[end..];
+            token.to_string()
+        };
+        parameters.push((name.to_ascii_lowercase(), parameter_value));
+    }
+
+    if !rest.is_empty() {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Unexpected data in: {}",
+            value
+        )));
+    }
+    Ok(parameters)
+}
+
+/// Names must be tokens. Values may hold anything a quoted-string can
+/// carry, which excludes control characters other than HTAB (RFC 9110
+/// section 5.6.4); CR and LF would otherwise end the header line.
+pub(crate) fn check_parameter(name: &str, value: &str) -> Result<(), ParseHttpError> {
+    if !is_token(name) {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid parameter name: {:?}",
+            name
+        )));
+    }
+    if value.bytes().any(|b| (b < b' ' && b != b'\t') || b == 0x7f) {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid parameter value: {:?}",
+            value
+        )));
+    }
+    Ok(())
+}
+
+/// Writes `; name=value` pairs, quoting values that are not tokens or,
+/// with `always_quote`, every value. RFC 8187 ext-values such as
+/// `filename*` may never be quoted, so those stay bare when they are tokens.
+pub(crate) fn write_parameters(
+    f: &mut fmt::Formatter<'_>,
+    parameters: &[(String, String)],
+    always_quote: bool,
+) -> fmt::Result {
+    for (name, value) in parameters {
+        if is_token(value) && (!always_quote || name.ends_with('*')) {
+            write!(f, "; {}={}", name, value)?;
+        } else {
+            f.write_str("; ")?;
+            f.write_str(name)?;
+            f.write_str("=\"")?;
+            for c in value.chars() {
+                if c == '"' || c == '\\' {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+            f.write_str("\"")?;
+        }
+    }
+    std::result::Result::Ok(())
+}
+
+pub(crate) fn is_ows(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// Whether the value is a non-empty RFC 9110 token.
+pub(crate) fn is_token(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(is_tchar)
+}
+
+/// A byte allowed in a token, for scanning where one ends.
+pub(crate) fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Parses a quoted-string at the start of `value`, returning the unescaped
+/// content and the number of bytes consumed including both quotes.
+pub(crate) fn parse_quoted_string(value: &str) -> Result<(String, usize), ParseHttpError> {
+    let mut result = String::new();
+    let mut chars = value.char_indices();
+    if !matches!(chars.next(), Some((_, '"'))) {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Expected quoted string: {}",
+            value
+        )));
+    }
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((result, index + 1)),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => result.push(escaped),
+                None => break,
+            },
+            _ => result.push(c),
+        }
+    }
+    Err(ParseHttpError::ParseHeaderError(format!(
+        "Unterminated quoted string: {}",
+        value
+    )))
+}
+
+#[cfg(test)]
+mod test_media_type {
+    use super::MediaType;
+    use crate::Result::Err;
+
+    #[test]
+    fn media_type_parse_test() {
+        let media_type =
+            MediaType::parse("Multipart/Form-Data ; Boundary=\"a \\\"b\\\" c\";charset=UTF-8;")
+                .unwrap();
+        assert!(media_type.is("multipart", "form-data"));
+        assert_eq!(media_type.essence(), "multipart/form-data");
+        assert_eq!(media_type.boundary(), Some("a \"b\" c"));
+        assert_eq!(media_type.charset(), Some("UTF-8"));
+        assert_eq!(
+            media_type.to_string(),
+            "multipart/form-data; boundary=\"a \\\"b\\\" c\"; charset=UTF-8"
+        );
+
+        assert!(matches!(MediaType::parse("text"), Err(_)));
+        assert!(matches!(MediaType::parse("text/plain; charset"), Err(_)));
+        assert!(matches!(MediaType::parse("text/plain; charset=\"utf-8"), Err(_)));
+        assert!(matches!(MediaType::parse("text/plain; charset=utf 8"), Err(_)));
+
+        let mut media_type = MediaType::new("text", "plain").unwrap();
+        assert!(matches!(media_type.set_param("charset", "utf-8\r\nX-Injected: 1"), Err(_)));
+        assert!(matches!(media_type.set_param("char set", "utf-8"), Err(_)));
+        media_type.set_param("title", "tab\tand \"quote\"").unwrap();
+        assert_eq!(media_type.to_string(), "text/plain; title=\"tab\tand \\\"quote\\\"\"");
+    }
+}
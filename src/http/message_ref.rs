@@ -0,0 +1,620 @@
+use crate::Result::{self, Err, Ok};
+use std::str::{self, FromStr};
+
+use super::chunked::{parse_chunk_line, ChunkedBody};
+use super::header_map::HeaderMap;
+use super::http::{is_field_value, Body, HeaderKey, HeaderValue, ParseHttpError};
+use super::http_message::{
+    validate_target_form, HttpRequest, HttpResponse, RequestMethod, RequestStartLine,
+    ResponseStartLine,
+};
+use super::media_type::is_token;
+use super::parser::{
+    check_host_count, request_body_framing, response_body_framing, BodyFraming, Leniency,
+    ParserLimits, Status,
+};
+use super::status::StatusCode;
+use super::uri::RequestTarget;
+use super::version::Version;
+
+/// A header field borrowed from the buffer it was parsed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeaderRef<'a> {
+    pub name: &'a str,
+    pub value: &'a [u8],
+}
+impl<'a> HeaderRef<'a> {
+    /// Compares the name ignoring case.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    /// The value as text, `None` if it carries obs-text that is not utf-8.
+    pub fn value_str(&self) -> Option<&'a str> {
+        str::from_utf8(self.value).ok()
+    }
+}
+
+/// A chunked body whose chunk data borrows from the parsed buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkedRef<'a> {
+    /// The encoded body, from the first chunk size line to the end of the
+    /// trailer section.
+    pub raw: &'a [u8],
+    pub chunks: Vec<&'a [u8]>,
+    pub trailers: Vec<HeaderRef<'a>>,
+}
+impl<'a> ChunkedRef<'a> {
+    pub fn data_len(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
+    pub fn to_owned(&self) -> Result<ChunkedBody, ParseHttpError> {
+        let (body, _) = ChunkedBody::parse(self.raw)?;
+        Ok(body)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BodyRef<'a> {
+    None,
+    Data(&'a [u8]),
+    Chunked(ChunkedRef<'a>),
+}
+impl<'a> BodyRef<'a> {
+    pub fn to_owned(&self) -> Result<Body, ParseHttpError> {
+        match self {
+            BodyRef::None => Ok(Body::None),
+            BodyRef::Data(data) => Ok(Body::Data(data.to_vec())),
+            BodyRef::Chunked(chunked) => Ok(Body::Chunked(chunked.to_owned()?)),
+        }
+    }
+}
+
+/// An HTTP/1.x request whose start line, headers and body borrow from the
+/// input buffer. Parsing is always strict (see `ParseMode::Strict`), as
+/// repairing a message would need a copy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequestRef<'a> {
+    pub method: &'a str,
+    pub target: &'a str,
+    pub version: Version,
+    pub headers: Vec<HeaderRef<'a>>,
+    pub body: BodyRef<'a>,
+}
+impl<'a> HttpRequestRef<'a> {
+    /// Parses the request at the start of `buffer`. `Complete` carries the
+    /// number of bytes the request occupies.
+    pub fn parse(buffer: &'a [u8]) -> Result<Status<HttpRequestRef<'a>>, ParseHttpError> {
+        HttpRequestRef::parse_with_limits(buffer, ParserLimits::default())
+    }
+
+    pub fn parse_with_limits(
+        buffer: &'a [u8],
+        limits: ParserLimits,
+    ) -> Result<Status<HttpRequestRef<'a>>, ParseHttpError> {
+        let Some(head) = parse_head(buffer, &limits)? else {
+            return Ok(Status::Partial);
+        };
+        let (method, target, version) = parse_request_line(head.start_line)?;
+        check_host_count(version, head.headers.iter().filter(|h| h.is("Host")).count())?;
+        check_framing_headers(&head.headers)?;
+        let framing = request_body_framing(
+            version,
+            field_values(&head.headers, "Transfer-Encoding"),
+            field_values(&head.headers, "Content-Length"),
+        )?;
+        let Some((body, end)) = parse_body(buffer, head.body_start, framing, &limits)? else {
+            return Ok(Status::Partial);
+        };
+        Ok(Status::Complete(
+            HttpRequestRef {
+                method,
+                target,
+                version,
+                headers: head.headers,
+                body,
+            },
+            end,
+        ))
+    }
+
+    /// The first header with this name.
+    pub fn header(&self, name: &str) -> Option<&HeaderRef<'a>> {
+        self.headers.iter().find(|header| header.is(name))
+    }
+
+    pub fn to_owned(&self) -> Result<HttpRequest, ParseHttpError> {
+        let method = RequestMethod::from_token(self.method)?;
+        let target = RequestTarget::parse(self.target)?;
+        validate_target_form(&method, &target)?;
+        Ok(HttpRequest {
+            start_line: RequestStartLine {
+                method,
+                target,
+                version: self.version,
+            },
+            headers: owned_headers(&self.headers)?,
+            body: self.body.to_owned()?,
+        })
+    }
+}
+
+/// An HTTP/1.x response borrowing from the input buffer, parsed strictly
+/// like `HttpRequestRef`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponseRef<'a> {
+    pub version: Version,
+    pub status: StatusCode,
+    /// The reason phrase, which may carry obs-text that is not utf-8.
+    pub reason: &'a [u8],
+    pub headers: Vec<HeaderRef<'a>>,
+    pub body: BodyRef<'a>,
+}
+impl<'a> HttpResponseRef<'a> {
+    /// Parses the response at the start of `buffer`. `request_method` is the
+    /// method of the request it answers, which decides whether HEAD and
+    /// CONNECT responses have a body. A body delimited by the end of the
+    /// connection stays `Partial`, see `parse_to_end`.
+    pub fn parse(
+        buffer: &'a [u8],
+        request_method: Option<&RequestMethod>,
+    ) -> Result<Status<HttpResponseRef<'a>>, ParseHttpError> {
+        HttpResponseRef::parse_with_limits(buffer, request_method, ParserLimits::default(), false)
+    }
+
+    /// Parses a response from a buffer holding everything the connection
+    /// delivered, so a close-delimited body extends to its end.
+    pub fn parse_to_end(
+        buffer: &'a [u8],
+        request_method: Option<&RequestMethod>,
+    ) -> Result<HttpResponseRef<'a>, ParseHttpError> {
+        match HttpResponseRef::parse_with_limits(
+            buffer,
+            request_method,
+            ParserLimits::default(),
+            true,
+        )? {
+            Status::Complete(response, _) => Ok(response),
+            Status::Partial => Err(ParseHttpError::IncompleteMessage),
+        }
+    }
+
+    /// Like `parse`, or `parse_to_end` when `closed` is set.
+    pub fn parse_with_limits(
+        buffer: &'a [u8],
+        request_method: Option<&RequestMethod>,
+        limits: ParserLimits,
+        closed: bool,
+    ) -> Result<Status<HttpResponseRef<'a>>, ParseHttpError> {
+        let Some(head) = parse_head(buffer, &limits)? else {
+            return Ok(Status::Partial);
+        };
+        let (version, status, reason) = parse_status_line(head.start_line)?;
+        check_framing_headers(&head.headers)?;
+        let framing = response_body_framing(
+            request_method,
+            version,
+            status,
+            field_values(&head.headers, "Transfer-Encoding"),
+            field_values(&head.headers, "Content-Length"),
+        )?;
+        let body = match framing {
+            BodyFraming::UntilClose if closed => {
+                let body = &buffer[head.body_start..];
+                limits.check_body_size(body.len())?;
+                Some((BodyRef::Data(body), buffer.len()))
+            }
+            framing => parse_body(buffer, head.body_start, framing, &limits)?,
+        };
+        let Some((body, end)) = body else {
+            return Ok(Status::Partial);
+        };
+        Ok(Status::Complete(
+            HttpResponseRef {
+                version,
+                status,
+                reason,
+                headers: head.headers,
+                body,
+            },
+            end,
+        ))
+    }
+
+    /// The first header with this name.
+    pub fn header(&self, name: &str) -> Option<&HeaderRef<'a>> {
+        self.headers.iter().find(|header| header.is(name))
+    }
+
+    pub fn to_owned(&self) -> Result<HttpResponse, ParseHttpError> {
+        Ok(HttpResponse {
+            start_line: ResponseStartLine {
+                version: self.version,
+                response_code: self.status,
+                response_msg: String::from_utf8_lossy(self.reason).into_owned(),
+            },
+            headers: owned_headers(&self.headers)?,
+            body: self.body.to_owned()?,
+        })
+    }
+}
+
+#[derive(Clone)]
+struct HeadRef<'a> {
+    start_line: &'a [u8],
+    headers: Vec<HeaderRef<'a>>,
+    body_start: usize,
+}
+
+fn parse_head<'a>(
+    buffer: &'a [u8],
+    limits: &ParserLimits,
+) -> Result<Option<HeadRef<'a>>, ParseHttpError> {
+    // Empty lines before the start line are ignored (RFC 9112 section 2.2)
+    let mut head_start = 0;
+    while buffer[head_start..].starts_with(b"\r\n") {
+        head_start += 2;
+    }
+    let head = &buffer[head_start..];
+    let Some(head_end) = head.windows(4).position(|window| window == b"\r\n\r\n") else {
+        limits.check_head(head, false)?;
+        return Ok(None);
+    };
+    limits.check_head(&head[..head_end + 4], true)?;
+
+    let mut lines = head[..head_end + 2]
+        .split_inclusive(|&b| b == b'\n')
+        .map(|line| match line.strip_suffix(b"\r\n") {
+            Some(line) => Ok(line),
+            None => Err(ParseHttpError::ParseHeaderError(Leniency::BareLf.to_string())),
+        });
+    let start_line = match lines.next() {
+        Some(line) => line?,
+        None => return Err(ParseHttpError::InvalidHttp),
+    };
+
+    let mut headers = Vec::new();
+    for line in lines {
+        if headers.len() == limits.max_headers {
+            return Err(ParseHttpError::TooManyHeaders(limits.max_headers));
+        }
+        headers.push(parse_header_line(line?)?);
+    }
+
+    Ok(Some(HeadRef {
+        start_line,
+        headers,
+        body_start: head_start + head_end + 4,
+    }))
+}
+
+fn parse_header_line(line: &[u8]) -> Result<HeaderRef<'_>, ParseHttpError> {
+    if line.starts_with(b" ") || line.starts_with(b"\t") {
+        return Err(ParseHttpError::ParseHeaderError(Leniency::ObsFold.to_string()));
+    }
+    let Some(colon) = line.iter().position(|&b| b == b':') else {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid Header: {}",
+            String::from_utf8_lossy(line)
+        )));
+    };
+    let name = match str::from_utf8(&line[..colon]) {
+        std::result::Result::Ok(name) if is_token(name) => name,
+        _ if line[..colon].ends_with(b" ") || line[..colon].ends_with(b"\t") => {
+            return Err(ParseHttpError::ParseHeaderError(
+                Leniency::WhitespaceBeforeColon.to_string(),
+            ))
+        }
+        _ => {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid header name: {:?}",
+                String::from_utf8_lossy(&line[..colon])
+            )))
+        }
+    };
+    // Strip the optional whitespace around the field value
+    let value = &line[colon + 1..];
+    let start = value
+        .iter()
+        .position(|&b| b != b' ' && b != b'\t')
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(start, |index| index + 1);
+    let value = &value[start..end];
+    if !is_field_value(value) {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Invalid header value: {:?}",
+            String::from_utf8_lossy(value)
+        )));
+    }
+    Ok(HeaderRef { name, value })
+}
+
+fn parse_request_line(line: &[u8]) -> Result<(&str, &str, Version), ParseHttpError> {
+    let mut parts = line.split(|&b| b == b' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(ParseHttpError::InvalidHttp);
+    };
+    let method = match str::from_utf8(method) {
+        std::result::Result::Ok(method) if is_token(method) => method,
+        _ => return Err(ParseHttpError::InvalidHttpMethod),
+    };
+    let target = match str::from_utf8(target) {
+        std::result::Result::Ok(target)
+            if !target.is_empty() && target.bytes().all(|b| b.is_ascii_graphic()) =>
+        {
+            target
+        }
+        _ => {
+            return Err(ParseHttpError::ParseError(format!(
+                "Invalid request target: {}",
+                String::from_utf8_lossy(target)
+            )))
+        }
+    };
+    // The target form is checked without parsing it: CONNECT takes an
+    // authority, `*` is for OPTIONS and the rest start with `/` or a scheme
+    let connect = method == "CONNECT";
+    let valid = if target == "*" {
+        method == "OPTIONS"
+    } else if target.starts_with('/') || target.contains("://") {
+        !connect
+    } else {
+        connect
+    };
+    if !valid {
+        return Err(ParseHttpError::ParseError(format!(
+            "Request target {} is not allowed for this method",
+            target
+        )));
+    }
+    Ok((method, target, Version::parse(version)?))
+}
+
+/// Parses `HTTP-version SP status-code SP [ reason-phrase ]` like the owned
+/// `ResponseStartLine`.
+fn parse_status_line(line: &[u8]) -> Result<(Version, StatusCode, &[u8]), ParseHttpError> {
+    let Some(version_end) = line.iter().position(|&b| b == b' ') else {
+        return Err(ParseHttpError::InvalidHttp);
+    };
+    let version = Version::parse(&line[..version_end])?;
+    let rest = &line[version_end + 1..];
+    let code_end = rest.iter().position(|&b| b == b' ').unwrap_or(rest.len());
+    let status = StatusCode::parse(&rest[..code_end])?;
+    let reason = rest.get(code_end + 1..).unwrap_or(&[]);
+    Ok((version, status, reason))
+}
+
+fn field_values<'h, 'a: 'h>(
+    headers: &'h [HeaderRef<'a>],
+    name: &'h str,
+) -> impl Iterator<Item = &'a str> + 'h {
+    // A value that is not utf-8 stays present and fails to parse
+    headers
+        .iter()
+        .filter(move |header| header.is(name))
+        .map(|header| header.value_str().unwrap_or(""))
+}
+
+/// The strict-mode checks of the streaming parser: no Content-Length next
+/// to Transfer-Encoding and no repeated Content-Length.
+fn check_framing_headers(headers: &[HeaderRef]) -> Result<(), ParseHttpError> {
+    let lengths = field_values(headers, "Content-Length")
+        .flat_map(|value| value.split(','))
+        .count();
+    if lengths > 0 && field_values(headers, "Transfer-Encoding").next().is_some() {
+        return Err(ParseHttpError::ParseHeaderError(
+            Leniency::ContentLengthWithTransferEncoding.to_string(),
+        ));
+    }
+    if lengths > 1 {
+        return Err(ParseHttpError::ParseHeaderError(
+            Leniency::DuplicateContentLength.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_body<'a>(
+    buffer: &'a [u8],
+    body_start: usize,
+    framing: BodyFraming,
+    limits: &ParserLimits,
+) -> Result<Option<(BodyRef<'a>, usize)>, ParseHttpError> {
+    match framing {
+        BodyFraming::None => Ok(Some((BodyRef::None, body_start))),
+        BodyFraming::Length(length) => {
+            limits.check_body_size(length)?;
+            let Some(end) = body_start.checked_add(length) else {
+                return Err(ParseHttpError::ParseHeaderError(format!(
+                    "Content-Length too large: {}",
+                    length
+                )));
+            };
+            if buffer.len() < end {
+                return Ok(None);
+            }
+            Ok(Some((BodyRef::Data(&buffer[body_start..end]), end)))
+        }
+        BodyFraming::Chunked => match parse_chunked(&buffer[body_start..], limits)? {
+            Some((chunked, length)) => Ok(Some((BodyRef::Chunked(chunked), body_start + length))),
+            None => Ok(None),
+        },
+        BodyFraming::UntilClose => {
+            limits.check_body_size(buffer.len() - body_start)?;
+            Ok(None)
+        }
+    }
+}
+
+fn parse_chunked<'a>(
+    data: &'a [u8],
+    limits: &ParserLimits,
+) -> Result<Option<(ChunkedRef<'a>, usize)>, ParseHttpError> {
+    // Chunk lines and trailers may add up to a header section
+    if let Some(max_body_size) = limits.max_body_size {
+        if data.len() > max_body_size.saturating_add(limits.max_header_bytes) {
+            return Err(ParseHttpError::BodyTooLarge(max_body_size));
+        }
+    }
+
+    let mut position = 0;
+    let mut chunks = Vec::new();
+    let mut data_size: usize = 0;
+    loop {
+        let Some(line) = next_line(data, &mut position) else {
+            return Ok(None);
+        };
+        let (size, _) = parse_chunk_line(line)?;
+        if size == 0 {
+            break;
+        }
+        data_size = data_size.saturating_add(size);
+        limits.check_body_size(data_size)?;
+        // Chunk sizes are at most isize::MAX, so this cannot overflow
+        if data.len() - position < size + 2 {
+            return Ok(None);
+        }
+        chunks.push(&data[position..position + size]);
+        if &data[position + size..position + size + 2] != b"\r\n" {
+            return Err(ParseHttpError::ParseBodyError(
+                "Missing CRLF after chunk data".to_string(),
+            ));
+        }
+        position += size + 2;
+    }
+
+    let mut trailers = Vec::new();
+    loop {
+        let Some(line) = next_line(data, &mut position) else {
+            return Ok(None);
+        };
+        if line.is_empty() {
+            break;
+        }
+        if trailers.len() == limits.max_headers {
+            return Err(ParseHttpError::TooManyHeaders(limits.max_headers));
+        }
+        trailers.push(parse_header_line(line)?);
+    }
+
+    Ok(Some((
+        ChunkedRef {
+            raw: &data[..position],
+            chunks,
+            trailers,
+        },
+        position,
+    )))
+}
+
+fn next_line<'a>(data: &'a [u8], position: &mut usize) -> Option<&'a [u8]> {
+    let line_end = data[*position..]
+        .windows(2)
+        .position(|window| window == b"\r\n")?;
+    let line = &data[*position..*position + line_end];
+    *position += line_end + 2;
+    Some(line)
+}
+
+fn owned_headers(headers: &[HeaderRef]) -> Result<HeaderMap, ParseHttpError> {
+    let mut result = HeaderMap::with_capacity(headers.len());
+    for header in headers {
+        let key = match HeaderKey::from_str(header.name) {
+            std::result::Result::Ok(key) => key,
+            std::result::Result::Err(e) => return Err(e),
+        };
+        let value = Into::<Result<HeaderValue, ParseHttpError>>::into(header.value)?;
+        result.append(key, value);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test_message_ref {
+    use super::{BodyRef, HttpRequestRef, HttpResponseRef};
+    use crate::http::http::Body;
+    use crate::http::http_message::{HttpRequest, RequestMethod};
+    use crate::http::parser::Status;
+    use crate::http::status::StatusCode;
+    use crate::Result::{self, Err};
+    use crate::http::http::ParseHttpError;
+
+    #[test]
+    fn request_ref_test() {
+        let input = b"POST /upload?x=1 HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nwiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\nGET / HTTP/1.1\r\n";
+        let (request, consumed) = match HttpRequestRef::parse(input).unwrap() {
+            Status::Complete(request, consumed) => (request, consumed),
+            Status::Partial => panic!("request should be complete"),
+        };
+        assert_eq!(&input[consumed..], b"GET / HTTP/1.1\r\n");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.target, "/upload?x=1");
+        assert_eq!(request.header("host").unwrap().value, b"example.com");
+        // The borrowed pieces point into the input
+        assert!(input.as_ptr_range().contains(&request.method.as_ptr()));
+        match &request.body {
+            BodyRef::Chunked(chunked) => {
+                assert_eq!(chunked.chunks, [&b"wiki"[..], &b"pedia"[..]]);
+                assert_eq!(chunked.trailers[0].name, "Expires");
+                assert!(input.as_ptr_range().contains(&chunked.chunks[0].as_ptr()));
+            }
+            _ => panic!("chunked body expected"),
+        }
+
+        let owned = request.to_owned().unwrap();
+        let expected: HttpRequest =
+            Into::<Result<HttpRequest, ParseHttpError>>::into(input[..consumed].to_vec()).unwrap();
+        assert_eq!(owned.start_line.target, expected.start_line.target);
+        assert!(matches!(owned.start_line.method, RequestMethod::POST));
+        match owned.body {
+            Body::Chunked(chunked) => assert_eq!(chunked.data(), b"wikipedia".to_vec()),
+            _ => panic!("chunked body expected"),
+        }
+
+        assert!(matches!(HttpRequestRef::parse(&input[..40]).unwrap(), Status::Partial));
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n";
+        assert!(matches!(HttpRequestRef::parse(input), Err(_)));
+        let input = b"GET / HTTP/1.1\r\nHost: a\r\nX: 1\nY: 2\r\n\r\n";
+        assert!(matches!(HttpRequestRef::parse(input), Err(_)));
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 18446744073709551615\r\n\r\n";
+        assert!(matches!(HttpRequestRef::parse(input), Err(_)));
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\nFFFFFFFFFFFFFFFF\r\nabc";
+        assert!(matches!(HttpRequestRef::parse(input), Err(_)));
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n7FFFFFFFFFFFFFFF\r\nabc";
+        assert!(matches!(HttpRequestRef::parse(input).unwrap(), Status::Partial));
+        let mut input = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n".to_vec();
+        input.extend(b"X: 1\r\n".repeat(101));
+        input.extend(b"\r\n");
+        assert!(matches!(HttpRequestRef::parse(&input), Err(ParseHttpError::TooManyHeaders(100))));
+    }
+
+    #[test]
+    fn response_ref_test() {
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        match HttpResponseRef::parse(input, None).unwrap() {
+            Status::Complete(response, consumed) => {
+                assert_eq!(consumed, input.len());
+                assert_eq!(response.status, StatusCode::OK);
+                assert_eq!(response.reason, b"OK");
+                assert_eq!(response.body, BodyRef::Data(b"hello"));
+                let owned = response.to_owned().unwrap();
+                assert_eq!(owned.start_line.response_msg, "OK");
+                assert!(matches!(owned.body, Body::Data(ref data) if data == b"hello"));
+            }
+            Status::Partial => panic!("response should be complete"),
+        }
+
+        let input = b"HTTP/1.0 200 OK\r\n\r\nuntil close";
+        assert!(matches!(HttpResponseRef::parse(input, None).unwrap(), Status::Partial));
+        let response = HttpResponseRef::parse_to_end(input, None).unwrap();
+        assert_eq!(response.body, BodyRef::Data(b"until close"));
+        let response = HttpResponseRef::parse_to_end(input, Some(&RequestMethod::HEAD)).unwrap();
+        assert_eq!(response.body, BodyRef::None);
+    }
+}
@@ -0,0 +1,1073 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::content_disposition::ContentDisposition;
+use super::header_map::HeaderMap;
+use super::http::{
+    generate_boundary, io_result, random_hex, Header, HeaderCase, HeaderKey, ParseHttpError, StandardHeaders,
+};
+use super::media_type::MediaType;
+use super::parser::ParserLimits;
+
+/// Something the multipart parser found in the bytes fed so far. Every part
+/// produces one `PartStart`, any number of `PartData` and one `PartEnd`.
+/// Text before the first and after the last boundary is reported as
+/// `Preamble` and `Epilogue` data.
+#[derive(Clone)]
+pub enum MultipartEvent {
+    Preamble(Vec<u8>),
+    PartStart(HeaderMap),
+    PartData(Vec<u8>),
+    PartEnd,
+    End,
+    Epilogue(Vec<u8>),
+}
+
+/// Size limits for a multipart body. `None` means unlimited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MultipartLimits {
+    pub max_part_size: Option<usize>,
+    pub max_total_size: Option<usize>,
+    pub max_parts: Option<usize>,
+    pub max_header_size: usize,
+}
+impl From<&ParserLimits> for MultipartLimits {
+    fn from(limits: &ParserLimits) -> Self {
+        Self {
+            max_total_size: limits.max_body_size,
+            max_header_size: limits.max_header_bytes,
+            ..MultipartLimits::default()
+        }
+    }
+}
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            max_part_size: None,
+            max_total_size: None,
+            max_parts: None,
+            max_header_size: 8 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MultipartState {
+    Preamble,
+    AfterDelimiter,
+    Headers,
+    Body,
+    Epilogue,
+}
+
+/// Incremental `multipart/*` parser (RFC 2046 section 5.1). Only the bytes
+/// that may still be part of a delimiter are kept between calls to `feed`,
+/// so memory use does not grow with the size of the parts.
+#[derive(Clone)]
+pub struct MultipartParser {
+    delimiter: Vec<u8>,
+    limits: MultipartLimits,
+    state: MultipartState,
+    buffer: Vec<u8>,
+    virtual_prefix: usize,
+    total_size: usize,
+    part_size: usize,
+    parts: usize,
+}
+impl MultipartParser {
+    pub fn new(boundary: &str) -> MultipartParser {
+        MultipartParser::with_limits(boundary, MultipartLimits::default())
+    }
+
+    pub fn with_limits(boundary: &str, limits: MultipartLimits) -> MultipartParser {
+        MultipartParser {
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            limits,
+            state: MultipartState::Preamble,
+            // The first delimiter may open the body without a leading CRLF
+            buffer: b"\r\n".to_vec(),
+            virtual_prefix: 2,
+            total_size: 0,
+            part_size: 0,
+            parts: 0,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == MultipartState::Epilogue
+    }
+
+    /// Parses as much of `data` as possible and returns the events found.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<MultipartEvent>, ParseHttpError> {
+        self.total_size += data.len();
+        if let Some(max_total_size) = self.limits.max_total_size {
+            if self.total_size > max_total_size {
+                return Err(ParseHttpError::BodyTooLarge(max_total_size));
+            }
+        }
+        if self.state == MultipartState::Epilogue {
+            if data.is_empty() {
+                return Ok(Vec::new());
+            }
+            return Ok(vec![MultipartEvent::Epilogue(data.to_vec())]);
+        }
+        self.buffer.extend_from_slice(data);
+
+        let mut events = Vec::new();
+        let mut position = 0;
+        loop {
+            let buffer = &self.buffer[position..];
+            match self.state {
+                MultipartState::Preamble => {
+                    let (preamble_end, next) = match find(buffer, &self.delimiter) {
+                        Some(index) => (index, Some(index + self.delimiter.len())),
+                        None => (buffer.len().saturating_sub(self.delimiter.len() - 1), None),
+                    };
+                    // Skip the CRLF that was added in front of the body
+                    let start = self.virtual_prefix.min(preamble_end);
+                    self.virtual_prefix -= start;
+                    if preamble_end > start {
+                        events.push(MultipartEvent::Preamble(buffer[start..preamble_end].to_vec()));
+                    }
+                    match next {
+                        Some(next) => {
+                            position += next;
+                            self.virtual_prefix = 0;
+                            self.state = MultipartState::AfterDelimiter;
+                        }
+                        None => {
+                            position += preamble_end;
+                            break;
+                        }
+                    }
+                }
+                MultipartState::AfterDelimiter => {
+                    if buffer.len() < 2 {
+                        break;
+                    }
+                    if buffer.starts_with(b"--") {
+                        self.state = MultipartState::Epilogue;
+                        events.push(MultipartEvent::End);
+                        // The epilogue keeps the line break ending the delimiter
+                        if buffer.len() > 2 {
+                            events.push(MultipartEvent::Epilogue(buffer[2..].to_vec()));
+                        }
+                        break;
+                    }
+                    let Some(line_end) = find(buffer, b"\r\n") else {
+                        if buffer.len() > self.limits.max_header_size {
+                            return Err(ParseHttpError::ParseFormDataError(
+                                "Missing CRLF after boundary".to_string(),
+                            ));
+                        }
+                        break;
+                    };
+                    // Transport padding may follow the boundary
+                    if !buffer[..line_end].iter().all(|&b| b == b' ' || b == b'\t') {
+                        return Err(ParseHttpError::ParseFormDataError(
+                            "Unexpected data after boundary".to_string(),
+                        ));
+                    }
+                    position += line_end + 2;
+                    self.parts += 1;
+                    if let Some(max_parts) = self.limits.max_parts {
+                        if self.parts > max_parts {
+                            return Err(ParseHttpError::ParseFormDataError(format!(
+                                "Multipart body has more than {} parts",
+                                max_parts
+                            )));
+                        }
+                    }
+                    self.state = MultipartState::Headers;
+                }
+                MultipartState::Headers => {
+                    // A part without headers starts directly with the blank line
+                    let (head, head_end) = if buffer.starts_with(b"\r\n") {
+                        (&buffer[0..0], 2)
+                    } else {
+                        match find(buffer, b"\r\n\r\n") {
+                            Some(index) => (&buffer[..index], index + 4),
+                            None => {
+                                if buffer.len() > self.limits.max_header_size {
+                                    return Err(ParseHttpError::HeadersTooLarge(
+                                        self.limits.max_header_size,
+                                    ));
+                                }
+                                break;
+                            }
+                        }
+                    };
+                    if head_end > self.limits.max_header_size {
+                        return Err(ParseHttpError::HeadersTooLarge(
+                            self.limits.max_header_size,
+                        ));
+                    }
+                    let headers = parse_part_headers(head)?;
+                    position += head_end;
+                    self.part_size = 0;
+                    self.state = MultipartState::Body;
+                    events.push(MultipartEvent::PartStart(headers));
+                }
+                MultipartState::Body => {
+                    let (data_end, next) = match find(buffer, &self.delimiter) {
+                        Some(index) => (index, Some(index + self.delimiter.len())),
+                        // Hold back bytes that could start a delimiter
+                        None => (buffer.len().saturating_sub(self.delimiter.len() - 1), None),
+                    };
+                    if data_end > 0 {
+                        self.part_size += data_end;
+                        if let Some(max_part_size) = self.limits.max_part_size {
+                            if self.part_size > max_part_size {
+                                return Err(ParseHttpError::BodyTooLarge(max_part_size));
+                            }
+                        }
+                        events.push(MultipartEvent::PartData(buffer[..data_end].to_vec()));
+                    }
+                    match next {
+                        Some(next) => {
+                            position += next;
+                            self.state = MultipartState::AfterDelimiter;
+                            events.push(MultipartEvent::PartEnd);
+                        }
+                        None => {
+                            position += data_end;
+                            break;
+                        }
+                    }
+                }
+                MultipartState::Epilogue => break,
+            }
+        }
+
+        if self.state == MultipartState::Epilogue {
+            self.buffer = Vec::new();
+        } else {
+            self.buffer.drain(..position);
+        }
+        Ok(events)
+    }
+
+    /// Signals the end of the body. Fails if the close delimiter was not seen.
+    pub fn finish(&self) -> Result<(), ParseHttpError> {
+        if self.is_done() {
+            Ok(())
+        } else {
+            Err(ParseHttpError::IncompleteMessage)
+        }
+    }
+}
+
+/// Returns the index of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let first = *needle.first()?;
+    let mut start = 0;
+    while start + needle.len() <= haystack.len() {
+        let index = start + haystack[start..].iter().position(|&b| b == first)?;
+        if haystack.len() - index < needle.len() {
+            return None;
+        }
+        if &haystack[index..index + needle.len()] == needle {
+            return Some(index);
+        }
+        start = index + 1;
+    }
+    None
+}
+
+/// A sink that records whether the `--boundary` delimiter occurs in the
+/// data written to it, including across writes, without buffering it.
+struct DelimiterScanner<'a> {
+    boundary: &'a [u8],
+    // Length of the longest suffix of the data so far that is a prefix of
+    // the delimiter
+    matched: usize,
+    found: bool,
+}
+impl<'a> DelimiterScanner<'a> {
+    fn new(boundary: &'a str) -> DelimiterScanner<'a> {
+        DelimiterScanner {
+            boundary: boundary.as_bytes(),
+            matched: 0,
+            found: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.boundary.len() + 2
+    }
+
+    fn at(&self, index: usize) -> u8 {
+        if index < 2 {
+            b'-'
+        } else {
+            self.boundary[index - 2]
+        }
+    }
+
+    /// Whether the delimiter continues with `bytes` at `start`.
+    fn continues_with(&self, start: usize, bytes: &[u8]) -> bool {
+        bytes
+            .iter()
+            .enumerate()
+            .all(|(index, &byte)| self.at(start + index) == byte)
+    }
+
+    /// Whether the first `length` bytes of the delimiter end with its first
+    /// `border` bytes.
+    fn is_border(&self, length: usize, border: usize) -> bool {
+        (0..border).all(|index| self.at(length - border + index) == self.at(index))
+    }
+}
+impl Write for DelimiterScanner<'_> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        if self.found || data.is_empty() {
+            return std::result::Result::Ok(data.len());
+        }
+        let length = self.len();
+        let mut matched = 0;
+        // Delimiters started by earlier writes
+        for border in (1..=self.matched).rev() {
+            if !self.is_border(self.matched, border) {
+                continue;
+            }
+            let rest = length - border;
+            if data.len() >= rest && self.continues_with(border, &data[..rest]) {
+                self.found = true;
+            } else if data.len() < rest && self.continues_with(border, data) {
+                matched = matched.max(border + data.len());
+            }
+        }
+        if data.len() >= length && data.windows(length).any(|window| self.continues_with(0, window)) {
+            self.found = true;
+        }
+        // Delimiters started by this write
+        if let Some(border) = (1..length.min(data.len() + 1))
+            .rev()
+            .find(|&border| self.continues_with(0, &data[data.len() - border..]))
+        {
+            matched = matched.max(border);
+        }
+        self.matched = matched;
+        std::result::Result::Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::result::Result::Ok(())
+    }
+}
+
+fn parse_part_headers(head: &[u8]) -> Result<HeaderMap, ParseHttpError> {
+    let mut headers = HeaderMap::new();
+    for line in head.split(|&b| b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let header = Into::<Result<Header, ParseHttpError>>::into(line)?;
+        headers.append(header.key, header.value);
+    }
+    Ok(headers)
+}
+
+/// Removes the file when the last handle to it is dropped.
+#[derive(Debug)]
+struct TempPath(PathBuf);
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A part body that was written to a temporary file. The file is deleted
+/// once every clone of the handle is dropped, unless it is persisted.
+#[derive(Clone, Debug)]
+pub struct SpooledFile {
+    path: Arc<TempPath>,
+    len: usize,
+}
+impl SpooledFile {
+    /// Creates the file under an unpredictable name, readable only by the
+    /// owner on unix since uploads may hold sensitive data.
+    fn create(directory: &Path) -> std::io::Result<(SpooledFile, File)> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        loop {
+            let path = directory.join(format!("kparser-multipart-{}", random_hex()));
+            match options.open(&path) {
+                std::result::Result::Ok(file) => {
+                    let spooled = SpooledFile {
+                        path: Arc::new(TempPath(path)),
+                        len: 0,
+                    };
+                    return std::result::Result::Ok((spooled, file));
+                }
+                std::result::Result::Err(error)
+                    if error.kind() == std::io::ErrorKind::AlreadyExists =>
+                {
+                    continue
+                }
+                std::result::Result::Err(error) => return std::result::Result::Err(error),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn read_all(&self) -> Result<Vec<u8>, ParseHttpError> {
+        let mut data = Vec::with_capacity(self.len);
+        io_result(File::open(self.path()).and_then(|mut file| file.read_to_end(&mut data)))?;
+        Ok(data)
+    }
+
+    /// Moves the file to `destination`, which then outlives this handle.
+    pub fn persist(self, destination: &Path) -> Result<(), ParseHttpError> {
+        io_result(fs::rename(self.path(), destination))
+    }
+}
+
+#[derive(Clone)]
+pub enum PartBody {
+    Memory(Vec<u8>),
+    File(SpooledFile),
+}
+impl PartBody {
+    pub fn len(&self) -> usize {
+        match self {
+            PartBody::Memory(data) => data.len(),
+            PartBody::File(file) => file.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The whole body, read back from disk if it was spooled.
+    pub fn to_vec(&self) -> Result<Vec<u8>, ParseHttpError> {
+        match self {
+            PartBody::Memory(data) => Ok(data.clone()),
+            PartBody::File(file) => file.read_all(),
+        }
+    }
+}
+
+/// A complete part gathered by `MultipartCollector`.
+#[derive(Clone)]
+pub struct MultipartPart {
+    pub headers: HeaderMap,
+    pub body: PartBody,
+}
+impl MultipartPart {
+    fn content_disposition(&self) -> Option<ContentDisposition> {
+        let value = self
+            .headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Disposition))?;
+        match ContentDisposition::parse(value.as_str()) {
+            Ok(disposition) => Some(disposition),
+            Err(_) => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<String> {
+        self.content_disposition()?.name().map(|name| name.to_string())
+    }
+
+    pub fn filename(&self) -> Option<String> {
+        self.content_disposition()?.filename()
+    }
+}
+
+struct SpoolOptions {
+    threshold: usize,
+    directory: PathBuf,
+}
+
+enum PartWriter {
+    Memory(Vec<u8>),
+    File(SpooledFile, File),
+}
+
+/// Collects the events of a `MultipartParser` into parts. With spooling
+/// enabled, a part is moved to a temporary file once it grows past the
+/// threshold, so only small parts are held in memory.
+pub struct MultipartCollector {
+    parser: MultipartParser,
+    spool: Option<SpoolOptions>,
+    parts: Vec<MultipartPart>,
+    current: Option<(HeaderMap, PartWriter)>,
+}
+impl MultipartCollector {
+    pub fn new(parser: MultipartParser) -> MultipartCollector {
+        MultipartCollector {
+            parser,
+            spool: None,
+            parts: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Spools parts larger than `threshold` bytes to files in `directory`.
+    pub fn spool_larger_than(mut self, threshold: usize, directory: PathBuf) -> MultipartCollector {
+        self.spool = Some(SpoolOptions {
+            threshold,
+            directory,
+        });
+        self
+    }
+
+    /// Spools parts larger than `threshold` bytes to the system temp directory.
+    pub fn spool_to_temp_dir(self, threshold: usize) -> MultipartCollector {
+        self.spool_larger_than(threshold, std::env::temp_dir())
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Result<(), ParseHttpError> {
+        for event in self.parser.feed(data)? {
+            match event {
+                MultipartEvent::PartStart(headers) => {
+                    self.current = Some((headers, PartWriter::Memory(Vec::new())));
+                }
+                MultipartEvent::PartData(data) => self.write(&data)?,
+                MultipartEvent::PartEnd => {
+                    let Some((headers, writer)) = self.current.take() else {
+                        continue;
+                    };
+                    let body = match writer {
+                        PartWriter::Memory(data) => PartBody::Memory(data),
+                        PartWriter::File(spooled, mut file) => {
+                            io_result(file.flush())?;
+                            PartBody::File(spooled)
+                        }
+                    };
+                    self.parts.push(MultipartPart { headers, body });
+                }
+                MultipartEvent::Preamble(_)
+                | MultipartEvent::End
+                | MultipartEvent::Epilogue(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), ParseHttpError> {
+        let Some((_, writer)) = &mut self.current else {
+            return Ok(());
+        };
+        if let PartWriter::Memory(buffer) = writer {
+            match &self.spool {
+                Some(spool) if buffer.len() + data.len() > spool.threshold => {
+                    let (mut spooled, mut file) = match SpooledFile::create(&spool.directory) {
+                        std::result::Result::Ok(created) => created,
+                        // The open file is not Clone, so `io_result` cannot carry it
+                        std::result::Result::Err(error) => return Err(error.into()),
+                    };
+                    io_result(file.write_all(buffer))?;
+                    spooled.len = buffer.len();
+                    *writer = PartWriter::File(spooled, file);
+                }
+                _ => {
+                    buffer.extend_from_slice(data);
+                    return Ok(());
+                }
+            }
+        }
+        if let PartWriter::File(spooled, file) = writer {
+            io_result(file.write_all(data))?;
+            spooled.len += data.len();
+        }
+        Ok(())
+    }
+
+    /// Returns the collected parts once the close delimiter has been seen.
+    pub fn finish(self) -> Result<Vec<MultipartPart>, ParseHttpError> {
+        self.parser.finish()?;
+        Ok(self.parts)
+    }
+}
+
+// Nested bodies beyond this depth are kept as opaque data
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// A parsed `multipart/*` body (RFC 2046 section 5.1) of any subtype, such as
+/// `mixed`, `alternative`, `related` or `byteranges`.
+#[derive(Clone)]
+pub struct Multipart {
+    pub media_type: MediaType,
+    /// Bytes before the first boundary, without the line break that starts it.
+    pub preamble: Vec<u8>,
+    pub parts: Vec<BodyPart>,
+    /// Bytes after the close delimiter, including the line break ending it.
+    pub epilogue: Vec<u8>,
+}
+impl Multipart {
+    /// An empty body of the given subtype with a generated boundary.
+    pub fn new(subtype: &str) -> Result<Multipart, ParseHttpError> {
+        let mut media_type = MediaType::new("multipart", subtype)?;
+        media_type.set_param("boundary", &generate_boundary())?;
+        Ok(Multipart {
+            media_type,
+            preamble: Vec::new(),
+            parts: Vec::new(),
+            epilogue: Vec::new(),
+        })
+    }
+
+    /// Parses `data` with the boundary and subtype given by `media_type`,
+    /// usually the message's Content-Type. Parts that are themselves
+    /// multipart are parsed as nested bodies.
+    pub fn parse(media_type: MediaType, data: &[u8]) -> Result<Multipart, ParseHttpError> {
+        Multipart::parse_nested(media_type, data, 0)
+    }
+
+    fn parse_nested(
+        media_type: MediaType,
+        data: &[u8],
+        depth: usize,
+    ) -> Result<Multipart, ParseHttpError> {
+        if media_type.type_() != "multipart" {
+            return Err(ParseHttpError::ParseBodyError(format!(
+                "Not a multipart media type: {}",
+                media_type.essence()
+            )));
+        }
+        let Some(boundary) = media_type.boundary() else {
+            return Err(ParseHttpError::FormdataBoundaryNotFound);
+        };
+        let mut parser = MultipartParser::new(boundary);
+        let events = parser.feed(data)?;
+        parser.finish()?;
+
+        let mut multipart = Multipart {
+            media_type: media_type.clone(),
+            preamble: Vec::new(),
+            parts: Vec::new(),
+            epilogue: Vec::new(),
+        };
+        let mut current: Option<(HeaderMap, Vec<u8>)> = None;
+        for event in events {
+            match event {
+                MultipartEvent::Preamble(data) => multipart.preamble.extend_from_slice(&data),
+                MultipartEvent::PartStart(headers) => current = Some((headers, Vec::new())),
+                MultipartEvent::PartData(data) => {
+                    if let Some((_, body)) = &mut current {
+                        body.extend_from_slice(&data);
+                    }
+                }
+                MultipartEvent::PartEnd => {
+                    let Some((headers, data)) = current.take() else {
+                        continue;
+                    };
+                    let content = match part_media_type(&headers) {
+                        Some(inner) if inner.type_() == "multipart" && depth < MAX_NESTING_DEPTH => {
+                            PartContent::Multipart(Multipart::parse_nested(inner, &data, depth + 1)?)
+                        }
+                        _ => PartContent::Data(data),
+                    };
+                    multipart.parts.push(BodyPart { headers, content });
+                }
+                MultipartEvent::End => {}
+                MultipartEvent::Epilogue(data) => multipart.epilogue.extend_from_slice(&data),
+            }
+        }
+        Ok(multipart)
+    }
+
+    pub fn subtype(&self) -> &str {
+        self.media_type.subtype()
+    }
+
+    pub fn boundary(&self) -> &str {
+        self.media_type.boundary().unwrap_or("")
+    }
+
+    pub fn add_part(&mut self, headers: HeaderMap, content: PartContent) -> &mut Multipart {
+        self.parts.push(BodyPart { headers, content });
+        self
+    }
+
+    /// The root of a `multipart/related` body: the part whose Content-ID
+    /// matches the `start` parameter, or the first part (RFC 2387).
+    pub fn root_part(&self) -> Option<&BodyPart> {
+        match self.media_type.param("start") {
+            Some(start) => {
+                let start = start.trim_start_matches('<').trim_end_matches('>');
+                self.parts.iter().find(|part| part.content_id() == Some(start))
+            }
+            None => self.parts.first(),
+        }
+    }
+
+    /// The `type` parameter of `multipart/related`, naming the root's type.
+    pub fn related_type(&self) -> Option<&str> {
+        self.media_type.param("type")
+    }
+
+    /// The part a `multipart/alternative` reader should prefer. Parts are in
+    /// increasing order of preference (RFC 2046 section 5.1.4).
+    pub fn preferred_alternative(&self) -> Option<&BodyPart> {
+        self.parts.last()
+    }
+
+    /// Serializes the body. Fails if the boundary occurs in a part.
+    pub fn encode(&self) -> Result<Vec<u8>, ParseHttpError> {
+        let mut result = Vec::new();
+        self.write_to(&mut result)?;
+        Ok(result)
+    }
+
+    /// Writes the body to `w`. Every part is checked for the boundary
+    /// before anything is written, so a failure leaves `w` untouched.
+    pub fn write_to(&self, w: &mut impl Write) -> Result<(), ParseHttpError> {
+        let boundary = self.boundary();
+        if boundary.is_empty() {
+            return Err(ParseHttpError::FormdataBoundaryNotFound);
+        }
+        for part in &self.parts {
+            let mut scanner = DelimiterScanner::new(boundary);
+            match &part.content {
+                PartContent::Data(data) => io_result(scanner.write_all(data))?,
+                PartContent::Multipart(multipart) => multipart.write_to(&mut scanner)?,
+            }
+            if scanner.found {
+                return Err(ParseHttpError::ParseBodyError(format!(
+                    "Boundary occurs in part content: {}",
+                    boundary
+                )));
+            }
+        }
+        io_result(self.write_unchecked(w))
+    }
+
+    fn write_unchecked(&self, w: &mut impl Write) -> std::io::Result<()> {
+        let boundary = self.boundary();
+        if !self.preamble.is_empty() {
+            w.write_all(&self.preamble)?;
+            w.write_all(b"\r\n")?;
+        }
+        for part in &self.parts {
+            write!(w, "--{}\r\n", boundary)?;
+            part.headers.write_to(w, HeaderCase::Original)?;
+            w.write_all(b"\r\n")?;
+            match &part.content {
+                PartContent::Data(data) => w.write_all(data)?,
+                PartContent::Multipart(multipart) => multipart.write_unchecked(w)?,
+            }
+            w.write_all(b"\r\n")?;
+        }
+        write!(w, "--{}--", boundary)?;
+        w.write_all(&self.epilogue)
+    }
+}
+impl From<&Multipart> for Result<Vec<u8>, ParseHttpError> {
+    fn from(multipart: &Multipart) -> Self {
+        multipart.encode()
+    }
+}
+
+#[derive(Clone)]
+pub enum PartContent {
+    Data(Vec<u8>),
+    Multipart(Multipart),
+}
+
+/// One part of a `Multipart` body.
+#[derive(Clone)]
+pub struct BodyPart {
+    pub headers: HeaderMap,
+    pub content: PartContent,
+}
+impl BodyPart {
+    pub fn new(headers: HeaderMap, data: Vec<u8>) -> BodyPart {
+        BodyPart {
+            headers,
+            content: PartContent::Data(data),
+        }
+    }
+
+    /// The part's Content-Type, `text/plain` when absent (RFC 2046 section 5.1).
+    pub fn content_type(&self) -> Result<MediaType, ParseHttpError> {
+        match self.headers.get(&HeaderKey::from(StandardHeaders::Content_Type)) {
+            Some(value) => MediaType::parse(value.as_str()),
+            None => MediaType::new("text", "plain"),
+        }
+    }
+
+    /// The Content-ID without its angle brackets.
+    pub fn content_id(&self) -> Option<&str> {
+        let value = self.headers.get(&HeaderKey::from(StandardHeaders::Content_ID))?;
+        Some(
+            value
+                .as_str()
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>'),
+        )
+    }
+
+    /// The Content-Range of a `multipart/byteranges` part.
+    pub fn content_range(&self) -> Option<Result<ContentRange, ParseHttpError>> {
+        self.headers
+            .get(&HeaderKey::from(StandardHeaders::Content_Range))
+            .map(|value| ContentRange::parse(value.as_str()))
+    }
+
+    /// The part data, or `None` for a nested multipart body.
+    pub fn data(&self) -> Option<&[u8]> {
+        match &self.content {
+            PartContent::Data(data) => Some(data),
+            PartContent::Multipart(_) => None,
+        }
+    }
+}
+
+/// A `bytes first-last/complete` Content-Range (RFC 9110 section 14.4).
+/// `complete_length` is `None` when the length is given as `*`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    pub first: u64,
+    pub last: u64,
+    pub complete_length: Option<u64>,
+}
+impl ContentRange {
+    pub fn parse(value: &str) -> Result<ContentRange, ParseHttpError> {
+        let invalid = || ParseHttpError::ParseHeaderError(format!("Invalid Content-Range: {}", value));
+        let Some(range) = value.trim().strip_prefix("bytes ") else {
+            return Err(invalid());
+        };
+        let Some((range, complete_length)) = range.split_once('/') else {
+            return Err(invalid());
+        };
+        let Some((first, last)) = range.split_once('-') else {
+            return Err(invalid());
+        };
+        let (std::result::Result::Ok(first), std::result::Result::Ok(last)) =
+            (first.parse::<u64>(), last.parse::<u64>())
+        else {
+            return Err(invalid());
+        };
+        let complete_length = match complete_length {
+            "*" => None,
+            length => match length.parse::<u64>() {
+                std::result::Result::Ok(length) => Some(length),
+                std::result::Result::Err(_) => return Err(invalid()),
+            },
+        };
+        if last < first || complete_length.is_some_and(|length| last >= length) {
+            return Err(invalid());
+        }
+        Ok(ContentRange {
+            first,
+            last,
+            complete_length,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.last - self.first + 1
+    }
+
+    /// Always false, a byte range includes at least its first byte.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+impl Display for ContentRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.complete_length {
+            Some(length) => write!(f, "bytes {}-{}/{}", self.first, self.last, length),
+            None => write!(f, "bytes {}-{}/*", self.first, self.last),
+        }
+    }
+}
+
+fn part_media_type(headers: &HeaderMap) -> Option<MediaType> {
+    let value = headers.get(&HeaderKey::from(StandardHeaders::Content_Type))?;
+    match MediaType::parse(value.as_str()) {
+        Ok(media_type) => Some(media_type),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test_multipart {
+    use super::{
+        ContentRange, DelimiterScanner, Multipart, MultipartCollector, MultipartEvent,
+        MultipartLimits, MultipartParser, PartBody, PartContent,
+    };
+    use crate::http::header_map::HeaderMap;
+    use crate::http::media_type::MediaType;
+    use std::io::Write;
+    use crate::Result::Err;
+
+    const BODY: &[u8] = b"preamble\r\n--xyz\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nfirst\r\n--xyz  \r\nContent-Disposition: form-data; name=\"b\"; filename=\"b.bin\"\r\n\r\n0123456789\r\n--x\r\n--xyz--\r\nepilogue";
+
+    #[test]
+    fn multipart_byte_by_byte_test() {
+        let mut parser = MultipartParser::new("xyz");
+        let mut events = Vec::new();
+        for byte in BODY {
+            events.extend(parser.feed(&[*byte]).unwrap());
+        }
+        parser.finish().unwrap();
+
+        let mut parts: Vec<(String, Vec<u8>)> = Vec::new();
+        for event in events {
+            match event {
+                MultipartEvent::PartStart(headers) => {
+                    let disposition = headers.iter().next().unwrap().1.as_str().to_string();
+                    parts.push((disposition, Vec::new()));
+                }
+                MultipartEvent::PartData(data) => parts.last_mut().unwrap().1.extend(data),
+                _ => {}
+            }
+        }
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].1, b"first");
+        assert_eq!(parts[1].1, b"0123456789\r\n--x");
+    }
+
+    #[test]
+    fn multipart_limits_test() {
+        let limits = MultipartLimits {
+            max_part_size: Some(8),
+            ..MultipartLimits::default()
+        };
+        let mut parser = MultipartParser::with_limits("xyz", limits);
+        assert!(matches!(parser.feed(BODY), Err(_)));
+
+        let limits = MultipartLimits {
+            max_total_size: Some(16),
+            ..MultipartLimits::default()
+        };
+        let mut parser = MultipartParser::with_limits("xyz", limits);
+        assert!(matches!(parser.feed(BODY), Err(_)));
+
+        let mut parser = MultipartParser::new("xyz");
+        parser.feed(&BODY[..40]).unwrap();
+        assert!(matches!(parser.finish(), Err(_)));
+    }
+
+    #[test]
+    fn multipart_spool_test() {
+        let mut collector = MultipartCollector::new(MultipartParser::new("xyz")).spool_to_temp_dir(6);
+        for piece in BODY.chunks(7) {
+            collector.feed(piece).unwrap();
+        }
+        let parts = collector.finish().unwrap();
+        assert_eq!(parts[0].name(), Some("a".to_string()));
+        assert!(matches!(parts[0].body, PartBody::Memory(ref data) if data == b"first"));
+
+        assert_eq!(parts[1].filename(), Some("b.bin".to_string()));
+        let PartBody::File(ref file) = parts[1].body else {
+            panic!("part was not spooled");
+        };
+        let path = file.path().to_path_buf();
+        assert_eq!(file.read_all().unwrap(), b"0123456789\r\n--x");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        drop(parts);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn multipart_nested_test() {
+        let data = b"This is the preamble.\r\n--outer\r\nContent-Type: text/plain\r\n\r\nIntro\r\n--outer\r\nContent-Type: multipart/alternative; boundary=inner\r\n\r\n--inner\r\n\r\nplain\r\n--inner\r\nContent-Type: text/html\r\n\r\n<b>html</b>\r\n--inner--\r\n--outer--\r\nThis is the epilogue.\r\n";
+        let media_type = MediaType::parse("multipart/mixed; boundary=outer").unwrap();
+        let multipart = Multipart::parse(media_type, data).unwrap();
+        assert_eq!(multipart.subtype(), "mixed");
+        assert_eq!(multipart.preamble, b"This is the preamble.");
+        assert_eq!(multipart.epilogue, b"\r\nThis is the epilogue.\r\n");
+        assert_eq!(multipart.parts.len(), 2);
+        assert_eq!(multipart.parts[0].data(), Some(&b"Intro"[..]));
+
+        let PartContent::Multipart(ref inner) = multipart.parts[1].content else {
+            panic!("nested body was not parsed");
+        };
+        assert_eq!(inner.subtype(), "alternative");
+        assert_eq!(inner.parts[0].content_type().unwrap().essence(), "text/plain");
+        let preferred = inner.preferred_alternative().unwrap();
+        assert_eq!(preferred.data(), Some(&b"<b>html</b>"[..]));
+
+        assert_eq!(multipart.encode().unwrap(), data.to_vec());
+    }
+
+    #[test]
+    fn multipart_boundary_collision_test() {
+        let scan = |pieces: &[&[u8]]| {
+            let mut scanner = DelimiterScanner::new("xyz");
+            for piece in pieces {
+                scanner.write_all(piece).unwrap();
+            }
+            scanner.found
+        };
+        assert!(scan(&[b"a-", b"-x", b"y", b"zb"]));
+        assert!(scan(&[b"---x", b"yz"]));
+        assert!(scan(&[b"a--xyzb"]));
+        assert!(!scan(&[b"--x", b"y-", b"-xy"]));
+        assert!(!scan(&[b"-xyz", b"xyz"]));
+
+        let mut outer = Multipart::new("mixed").unwrap();
+        let mut inner = Multipart::new("alternative").unwrap();
+        let collision = format!("text\r\n--{}\r\n", outer.boundary());
+        inner.add_part(HeaderMap::new(), PartContent::Data(collision.into_bytes()));
+        outer.add_part(HeaderMap::new(), PartContent::Multipart(inner));
+        let mut out = Vec::new();
+        assert!(matches!(outer.write_to(&mut out), Err(_)));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn multipart_related_and_byteranges_test() {
+        let data = b"--r\r\nContent-ID: <first>\r\n\r\none\r\n--r\r\nContent-ID: <root@example>\r\n\r\ntwo\r\n--r--";
+        let media_type = MediaType::parse(
+            "multipart/related; boundary=r; type=\"application/xop+xml\"; start=\"<root@example>\"",
+        )
+        .unwrap();
+        let related = Multipart::parse(media_type, data).unwrap();
+        assert_eq!(related.related_type(), Some("application/xop+xml"));
+        assert_eq!(related.root_part().unwrap().data(), Some(&b"two"[..]));
+
+        let data = b"--br\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-3/10\r\n\r\nabcd\r\n--br\r\nContent-Range: bytes 8-9/10\r\n\r\nij\r\n--br--";
+        let media_type = MediaType::parse("multipart/byteranges; boundary=br").unwrap();
+        let ranges = Multipart::parse(media_type, data).unwrap();
+        let range = ranges.parts[1].content_range().unwrap().unwrap();
+        assert_eq!(
+            range,
+            ContentRange {
+                first: 8,
+                last: 9,
+                complete_length: Some(10)
+            }
+        );
+        assert_eq!(range.to_string(), "bytes 8-9/10");
+        assert!(matches!(ContentRange::parse("bytes 5-2/10"), Err(_)));
+
+        let mut built = Multipart::new("mixed").unwrap();
+        built.add_part(Default::default(), PartContent::Data(b"x".to_vec()));
+        let encoded = built.encode().unwrap();
+        let reparsed = Multipart::parse(built.media_type.clone(), &encoded).unwrap();
+        assert_eq!(reparsed.parts[0].data(), Some(&b"x"[..]));
+    }
+}
@@ -0,0 +1,927 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::chunked::ChunkedDecoder;
+use super::header_map::HeaderMap;
+use super::http::{Body, Header, HeaderKey, HeaderValue, ParseHttpError, StandardHeaders};
+use super::http_message::{
+    HttpRequest, HttpResponse, RequestMethod, RequestStartLine, ResponseStartLine,
+};
+use super::status::StatusCode;
+use super::version::Version;
+
+/// Outcome of feeding bytes into a parser. `Complete` carries the parsed
+/// message and how many bytes of the last fed slice belong to it; anything
+/// after that is the start of the next message.
+#[derive(Clone)]
+pub enum Status<T> {
+    Partial,
+    Complete(T, usize),
+}
+
+/// How the end of a message body is found (RFC 9112 section 6.3).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BodyFraming {
+    None,
+    Length(usize),
+    Chunked,
+    UntilClose,
+}
+
+/// Caps on how much of a message the parsers buffer. Each limit fails
+/// with its own error, see `ParseHttpError::status_code`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParserLimits {
+    /// Longest request or status line, without its CRLF.
+    pub max_start_line: usize,
+    pub max_headers: usize,
+    /// Size of the header section following the start line.
+    pub max_header_bytes: usize,
+    /// Largest body, counted as decoded data for chunked bodies. `None`
+    /// means unlimited.
+    pub max_body_size: Option<usize>,
+}
+impl Default for ParserLimits {
+    fn default() -> Self {
+        Self {
+            max_start_line: 8 * 1024,
+            max_headers: 100,
+            max_header_bytes: 64 * 1024,
+            max_body_size: None,
+        }
+    }
+}
+impl ParserLimits {
+    /// Checks the head received so far, `complete` once its end was found.
+    pub(crate) fn check_head(&self, head: &[u8], complete: bool) -> Result<(), ParseHttpError> {
+        let line_end = head.iter().position(|&b| b == b'\n');
+        let start_line = &head[..line_end.unwrap_or(head.len())];
+        let start_line = start_line.strip_suffix(b"\r").unwrap_or(start_line);
+        if start_line.len() > self.max_start_line {
+            return Err(ParseHttpError::StartLineTooLong(self.max_start_line));
+        }
+        if let Some(line_end) = line_end {
+            // The blank line ending the section is not counted
+            let trailing = if complete { 2 } else { 0 };
+            if head.len() - line_end - 1 > self.max_header_bytes + trailing {
+                return Err(ParseHttpError::HeadersTooLarge(self.max_header_bytes));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_body_size(&self, size: usize) -> Result<(), ParseHttpError> {
+        match self.max_body_size {
+            Some(max_body_size) if size > max_body_size => {
+                Err(ParseHttpError::BodyTooLarge(max_body_size))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How strictly message heads are checked. `Strict` rejects the framing
+/// ambiguities RFC 9112 lists as request smuggling vectors; `Lenient`
+/// accepts them for legacy peers and records each one as a `Leniency`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    #[default]
+    Strict,
+    Lenient,
+}
+
+/// A deviation from RFC 9112 that a lenient parser accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Leniency {
+    /// A line ended with LF instead of CRLF. The line is used as is.
+    BareLf,
+    /// A header continued on the next line. The lines are joined with a
+    /// space; continuation lines directly after the start line are ignored.
+    ObsFold,
+    /// Whitespace between a header name and its colon. It is removed.
+    WhitespaceBeforeColon,
+    /// Both Content-Length and Transfer-Encoding were present. The
+    /// Content-Length is removed and the message is framed by its coding.
+    ContentLengthWithTransferEncoding,
+    /// Content-Length was repeated with identical values. A single field
+    /// is kept.
+    DuplicateContentLength,
+}
+impl Leniency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Leniency::BareLf => "Bare LF line ending",
+            Leniency::ObsFold => "Obsolete line folding",
+            Leniency::WhitespaceBeforeColon => "Whitespace before header colon",
+            Leniency::ContentLengthWithTransferEncoding => "Content-Length with Transfer-Encoding",
+            Leniency::DuplicateContentLength => "Duplicate Content-Length",
+        }
+    }
+}
+impl Display for Leniency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Applies the parse mode and collects what was tolerated in one head.
+#[derive(Clone, Default)]
+struct Tolerance {
+    mode: ParseMode,
+    tolerated: Vec<Leniency>,
+}
+impl Tolerance {
+    fn allow(&mut self, leniency: Leniency) -> Result<(), ParseHttpError> {
+        match self.mode {
+            ParseMode::Strict => Err(ParseHttpError::ParseHeaderError(leniency.to_string())),
+            ParseMode::Lenient => {
+                if !self.tolerated.contains(&leniency) {
+                    self.tolerated.push(leniency);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct MessageHead<S> {
+    start_line: S,
+    headers: HeaderMap,
+    body_start: usize,
+    framing: BodyFraming,
+}
+
+struct MessageParser<S> {
+    limits: ParserLimits,
+    tolerance: Tolerance,
+    buffer: Vec<u8>,
+    scanned: usize,
+    head: Option<MessageHead<S>>,
+    chunked: Option<ChunkedDecoder>,
+}
+impl<S: Clone> MessageParser<S>
+where
+    Vec<u8>: Into<Result<S, ParseHttpError>>,
+{
+    fn new(limits: ParserLimits) -> Self {
+        Self {
+            limits,
+            tolerance: Tolerance::default(),
+            buffer: Vec::new(),
+            scanned: 0,
+            head: None,
+            chunked: None,
+        }
+    }
+
+    fn feed<F>(
+        &mut self,
+        data: &[u8],
+        framing: F,
+    ) -> Result<Option<(MessageHead<S>, Body, usize)>, ParseHttpError>
+    where
+        F: Fn(&S, &HeaderMap) -> Result<BodyFraming, ParseHttpError>,
+    {
+        let previous = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+
+        if self.head.is_none() {
+            // Empty lines before the start line are ignored (RFC 9112 section
+            // 2.2), so they must not be taken for the end of the head
+            let mut head_start = 0;
+            while self.buffer[head_start..].starts_with(b"\r\n") {
+                head_start += 2;
+            }
+            if head_start > self.limits.max_header_bytes {
+                return Err(ParseHttpError::HeadersTooLarge(self.limits.max_header_bytes));
+            }
+            let from = self.scanned.max(head_start);
+            let Some(header_end) = find_header_end(&self.buffer, from) else {
+                self.limits.check_head(&self.buffer[head_start..], false)?;
+                // Keep the last three bytes, the blank line may straddle two feeds
+                self.scanned = self.buffer.len().saturating_sub(3);
+                return Ok(None);
+            };
+            self.limits.check_head(&self.buffer[head_start..header_end], true)?;
+            self.tolerance.tolerated.clear();
+            let (start_line, mut headers) = parse_head::<S>(
+                &self.buffer[head_start..header_end],
+                self.limits.max_headers,
+                &mut self.tolerance,
+            )?;
+            check_framing_headers(&mut headers, &mut self.tolerance)?;
+            let framing = framing(&start_line, &headers)?;
+            if let BodyFraming::Length(length) = framing {
+                self.limits.check_body_size(length)?;
+                if header_end.checked_add(length).is_none() {
+                    return Err(ParseHttpError::ParseHeaderError(format!(
+                        "Content-Length too large: {}",
+                        length
+                    )));
+                }
+            }
+            self.head = Some(MessageHead {
+                start_line,
+                headers,
+                body_start: header_end,
+                framing,
+            });
+        }
+
+        let (body_start, framing) = match &self.head {
+            Some(head) => (head.body_start, head.framing),
+            None => unreachable!(),
+        };
+        let (body, message_end) = match framing {
+            BodyFraming::None => (Body::None, body_start),
+            BodyFraming::Length(length) => {
+                // The head was rejected if this overflows
+                let end = body_start + length;
+                if self.buffer.len() < end {
+                    return Ok(None);
+                }
+                (Body::Data(self.buffer[body_start..end].to_vec()), end)
+            }
+            BodyFraming::Chunked => {
+                let max_body_size = self.limits.max_body_size;
+                let decoder = self
+                    .chunked
+                    .get_or_insert_with(|| ChunkedDecoder::with_max_data_size(max_body_size));
+                // Chunk lines and trailers may add up to a header section
+                if let Some(max_body_size) = max_body_size {
+                    let encoded = self.buffer.len() - body_start;
+                    if encoded > max_body_size.saturating_add(self.limits.max_header_bytes) {
+                        return Err(ParseHttpError::BodyTooLarge(max_body_size));
+                    }
+                }
+                match decoder.decode(&self.buffer[body_start..])? {
+                    Some(length) => match self.chunked.take() {
+                        Some(decoder) => (Body::Chunked(decoder.into_body()), body_start + length),
+                        None => unreachable!(),
+                    },
+                    None => return Ok(None),
+                }
+            }
+            BodyFraming::UntilClose => {
+                self.limits
+                    .check_body_size(self.buffer.len() - body_start)?;
+                return Ok(None);
+            }
+        };
+
+        let head = match self.head.take() {
+            Some(head) => head,
+            None => unreachable!(),
+        };
+        let consumed = message_end - previous;
+        self.reset();
+
+        Ok(Some((head, body, consumed)))
+    }
+
+    /// Signals that the connection was closed. Completes a close-delimited
+    /// message and fails if a message was cut short.
+    fn finish(&mut self) -> Result<Option<(MessageHead<S>, Body)>, ParseHttpError> {
+        match self.head.take() {
+            Some(head) if head.framing == BodyFraming::UntilClose => {
+                let body = Body::Data(self.buffer[head.body_start..].to_vec());
+                self.reset();
+                Ok(Some((head, body)))
+            }
+            Some(_) => Err(ParseHttpError::IncompleteMessage),
+            None if self.buffer.is_empty() => Ok(None),
+            None => Err(ParseHttpError::IncompleteMessage),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.scanned = 0;
+        self.head = None;
+        self.chunked = None;
+    }
+}
+
+/// Returns the index just past the blank line that ends the header
+/// section. Bare LF line endings are found too, `parse_head` decides
+/// whether they are accepted.
+fn find_header_end(buffer: &[u8], from: usize) -> Option<usize> {
+    let mut index = from;
+    while let Some(position) = buffer[index..].iter().position(|&b| b == b'\n') {
+        let line_start = index + position + 1;
+        match &buffer[line_start..] {
+            [b'\n', ..] => return Some(line_start + 1),
+            [b'\r', b'\n', ..] => return Some(line_start + 2),
+            _ => index = line_start,
+        }
+    }
+    None
+}
+
+fn parse_head<S: Clone>(
+    head: &[u8],
+    max_headers: usize,
+    tolerance: &mut Tolerance,
+) -> Result<(S, HeaderMap), ParseHttpError>
+where
+    Vec<u8>: Into<Result<S, ParseHttpError>>,
+{
+    let mut lines: Vec<Vec<u8>> = Vec::new();
+    for line in head.split_inclusive(|&b| b == b'\n') {
+        let line = match line.strip_suffix(b"\r\n") {
+            Some(line) => line,
+            None => {
+                tolerance.allow(Leniency::BareLf)?;
+                line.strip_suffix(b"\n").unwrap_or(line)
+            }
+        };
+        // Empty lines before the start line are ignored (RFC 9112 section 2.2)
+        if line.is_empty() {
+            continue;
+        }
+        if line[0] == b' ' || line[0] == b'\t' {
+            tolerance.allow(Leniency::ObsFold)?;
+            if lines.len() > 1 {
+                if let Some(previous) = lines.last_mut() {
+                    previous.push(b' ');
+                    previous.extend_from_slice(trim_whitespace(line));
+                }
+            }
+            continue;
+        }
+        lines.push(line.to_vec());
+    }
+
+    let mut lines = lines.into_iter();
+    let start_line = match lines.next() {
+        Some(line) => Into::<Result<S, ParseHttpError>>::into(line)?,
+        None => return Err(ParseHttpError::InvalidHttp),
+    };
+
+    let mut headers = HeaderMap::new();
+    for mut line in lines {
+        if headers.len() == max_headers {
+            return Err(ParseHttpError::TooManyHeaders(max_headers));
+        }
+        if let Some(colon) = line.iter().position(|&b| b == b':') {
+            let name_end = line[..colon]
+                .iter()
+                .rposition(|&b| b != b' ' && b != b'\t')
+                .map_or(0, |index| index + 1);
+            if name_end < colon {
+                tolerance.allow(Leniency::WhitespaceBeforeColon)?;
+                line.drain(name_end..colon);
+            }
+        }
+        let header = Into::<Result<Header, ParseHttpError>>::into(line)?;
+        headers.append(header.key, header.value);
+    }
+
+    Ok((start_line, headers))
+}
+
+fn trim_whitespace(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|&b| b != b' ' && b != b'\t')
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(start, |index| index + 1);
+    &value[start..end]
+}
+
+fn transfer_encoding_key() -> HeaderKey {
+    HeaderKey::from(StandardHeaders::Transfer_Encoding)
+}
+
+fn content_length_key() -> HeaderKey {
+    HeaderKey::from(StandardHeaders::Content_Length)
+}
+
+/// Whether `chunked` is the final transfer coding applied to the message.
+/// Repeated Transfer-Encoding fields form a single list.
+fn is_chunked<'v>(transfer_encoding: impl Iterator<Item = &'v str>) -> bool {
+    transfer_encoding
+        .last()
+        .and_then(|value| value.rsplit(',').next())
+        .map(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false)
+}
+
+/// Parses the Content-Length fields, accepting repeated fields and lists
+/// of identical values as allowed by RFC 9110 section 8.6.
+pub(crate) fn parse_content_length<'v>(
+    content_length: impl Iterator<Item = &'v str>,
+) -> Result<Option<usize>, ParseHttpError> {
+    let mut result = None;
+    for value in content_length {
+        for length in value.split(',') {
+            let length = match usize::from_str(length.trim()) {
+                std::result::Result::Ok(length) => length,
+                std::result::Result::Err(_) => {
+                    return Err(ParseHttpError::ParseHeaderError(format!(
+                        "Invalid Content-Length: {}",
+                        value
+                    )))
+                }
+            };
+            match result {
+                Some(previous) if previous != length => {
+                    return Err(ParseHttpError::ParseHeaderError(format!(
+                        "Conflicting Content-Length: {}",
+                        value
+                    )))
+                }
+                _ => result = Some(length),
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Rejects or, in lenient mode, repairs the Content-Length ambiguities
+/// that let two parsers disagree on where a message ends (RFC 9112
+/// section 6.3). Conflicting Content-Length values are always an error.
+fn check_framing_headers(
+    headers: &mut HeaderMap,
+    tolerance: &mut Tolerance,
+) -> Result<(), ParseHttpError> {
+    if !headers.contains_key(&content_length_key()) {
+        return Ok(());
+    }
+    if headers.contains_key(&transfer_encoding_key()) {
+        tolerance.allow(Leniency::ContentLengthWithTransferEncoding)?;
+        headers.remove(&content_length_key());
+        return Ok(());
+    }
+    let values = headers
+        .get_all(&content_length_key())
+        .flat_map(|value| value.as_str().split(','))
+        .count();
+    if values > 1 {
+        let content_length = headers.get_all(&content_length_key()).map(HeaderValue::as_str);
+        if let Some(length) = parse_content_length(content_length)? {
+            tolerance.allow(Leniency::DuplicateContentLength)?;
+            headers.insert(content_length_key(), HeaderValue::new(length.to_string())?);
+        }
+    }
+    Ok(())
+}
+
+/// Checks the version-dependent rules for a request head: HTTP/1.1 requests
+/// need exactly one Host (RFC 9112 section 3.2), and none may have more.
+pub fn validate_request_head(
+    start_line: &RequestStartLine,
+    headers: &HeaderMap,
+) -> Result<(), ParseHttpError> {
+    let hosts = headers
+        .get_all(&HeaderKey::from(StandardHeaders::Host))
+        .count();
+    check_host_count(start_line.version, hosts)
+}
+
+pub(crate) fn check_host_count(version: Version, hosts: usize) -> Result<(), ParseHttpError> {
+    if hosts > 1 {
+        return Err(ParseHttpError::ParseHeaderError(
+            "Multiple Host headers".to_string(),
+        ));
+    }
+    if hosts == 0 && version.requires_host() {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Missing Host header in {} request",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// An HTTP/1.0 message with Transfer-Encoding has faulty framing
+/// (RFC 9112 section 6.1).
+fn check_transfer_encoding_version(
+    version: Version,
+    has_transfer_encoding: bool,
+) -> Result<(), ParseHttpError> {
+    if !version.supports_chunked() && has_transfer_encoding {
+        return Err(ParseHttpError::ParseHeaderError(format!(
+            "Transfer-Encoding in {} message",
+            version
+        )));
+    }
+    Ok(())
+}
+
+pub fn request_framing(
+    start_line: &RequestStartLine,
+    headers: &HeaderMap,
+) -> Result<BodyFraming, ParseHttpError> {
+    request_body_framing(
+        start_line.version,
+        headers.get_all(&transfer_encoding_key()).map(HeaderValue::as_str),
+        headers.get_all(&content_length_key()).map(HeaderValue::as_str),
+    )
+}
+
+/// Request framing from the raw Transfer-Encoding and Content-Length values.
+pub(crate) fn request_body_framing<'v>(
+    version: Version,
+    transfer_encoding: impl Iterator<Item = &'v str>,
+    content_length: impl Iterator<Item = &'v str>,
+) -> Result<BodyFraming, ParseHttpError> {
+    let mut transfer_encoding = transfer_encoding.peekable();
+    let has_transfer_encoding = transfer_encoding.peek().is_some();
+    check_transfer_encoding_version(version, has_transfer_encoding)?;
+    if has_transfer_encoding {
+        if is_chunked(transfer_encoding) {
+            return Ok(BodyFraming::Chunked);
+        }
+        return Err(ParseHttpError::ParseHeaderError(
+            "Transfer-Encoding without chunked".to_string(),
+        ));
+    }
+    match parse_content_length(content_length)? {
+        Some(length) => Ok(BodyFraming::Length(length)),
+        None => Ok(BodyFraming::None),
+    }
+}
+
+pub fn response_framing(
+    request_method: Option<&RequestMethod>,
+    start_line: &ResponseStartLine,
+    headers: &HeaderMap,
+) -> Result<BodyFraming, ParseHttpError> {
+    response_body_framing(
+        request_method,
+        start_line.version,
+        start_line.response_code,
+        headers.get_all(&transfer_encoding_key()).map(HeaderValue::as_str),
+        headers.get_all(&content_length_key()).map(HeaderValue::as_str),
+    )
+}
+
+/// Response framing from the raw Transfer-Encoding and Content-Length values.
+pub(crate) fn response_body_framing<'v>(
+    request_method: Option<&RequestMethod>,
+    version: Version,
+    status: StatusCode,
+    transfer_encoding: impl Iterator<Item = &'v str>,
+    content_length: impl Iterator<Item = &'v str>,
+) -> Result<BodyFraming, ParseHttpError> {
+    let code = status.as_u16();
+    if matches!(request_method, Some(RequestMethod::HEAD))
+        || (100..200).contains(&code)
+        || code == 204
+        || code == 304
+    {
+        return Ok(BodyFraming::None);
+    }
+    if matches!(request_method, Some(RequestMethod::CONNECT)) && (200..300).contains(&code) {
+        return Ok(BodyFraming::None);
+    }
+    let mut transfer_encoding = transfer_encoding.peekable();
+    let has_transfer_encoding = transfer_encoding.peek().is_some();
+    check_transfer_encoding_version(version, has_transfer_encoding)?;
+    if has_transfer_encoding {
+        if is_chunked(transfer_encoding) {
+            return Ok(BodyFraming::Chunked);
+        }
+        return Ok(BodyFraming::UntilClose);
+    }
+    match parse_content_length(content_length)? {
+        Some(length) => Ok(BodyFraming::Length(length)),
+        None => Ok(BodyFraming::UntilClose),
+    }
+}
+
+pub struct HttpRequestParser {
+    inner: MessageParser<RequestStartLine>,
+}
+impl Default for HttpRequestParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl HttpRequestParser {
+    pub fn new() -> Self {
+        Self::with_limits(ParserLimits::default())
+    }
+
+    pub fn with_limits(limits: ParserLimits) -> Self {
+        Self {
+            inner: MessageParser::new(limits),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ParseMode) -> Self {
+        self.inner.tolerance.mode = mode;
+        self
+    }
+
+    /// What lenient mode accepted in the last request head parsed.
+    pub fn tolerated(&self) -> &[Leniency] {
+        &self.inner.tolerance.tolerated
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Result<Status<HttpRequest>, ParseHttpError> {
+        let result = self.inner.feed(data, |start_line, headers| {
+            validate_request_head(start_line, headers)?;
+            request_framing(start_line, headers)
+        })?;
+        match result {
+            Some((head, body, consumed)) => Ok(Status::Complete(
+                HttpRequest {
+                    start_line: head.start_line,
+                    headers: head.headers,
+                    body,
+                },
+                consumed,
+            )),
+            None => Ok(Status::Partial),
+        }
+    }
+}
+
+pub struct HttpResponseParser {
+    inner: MessageParser<ResponseStartLine>,
+    request_method: Option<RequestMethod>,
+}
+impl Default for HttpResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl HttpResponseParser {
+    pub fn new() -> Self {
+        Self::with_limits(ParserLimits::default())
+    }
+
+    pub fn with_limits(limits: ParserLimits) -> Self {
+        Self {
+            inner: MessageParser::new(limits),
+            request_method: None,
+        }
+    }
+
+    /// Sets the method of the request the next response answers, so that
+    /// responses to HEAD and CONNECT are framed without a body.
+    pub fn set_request_method(&mut self, method: RequestMethod) {
+        self.request_method = Some(method);
+    }
+
+    pub fn with_mode(mut self, mode: ParseMode) -> Self {
+        self.inner.tolerance.mode = mode;
+        self
+    }
+
+    /// What lenient mode accepted in the last response head parsed.
+    pub fn tolerated(&self) -> &[Leniency] {
+        &self.inner.tolerance.tolerated
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Result<Status<HttpResponse>, ParseHttpError> {
+        let request_method = self.request_method.clone();
+        let result = self.inner.feed(data, |start_line, headers| {
+            response_framing(request_method.as_ref(), start_line, headers)
+        })?;
+        match result {
+            Some((head, body, consumed)) => Ok(Status::Complete(
+                self.complete(head, body),
+                consumed,
+            )),
+            None => Ok(Status::Partial),
+        }
+    }
+
+    /// Signals end of input. Returns the pending close-delimited response,
+    /// `None` if nothing was buffered, or an error for a truncated message.
+    pub fn finish(&mut self) -> Result<Option<HttpResponse>, ParseHttpError> {
+        match self.inner.finish()? {
+            Some((head, body)) => Ok(Some(self.complete(head, body))),
+            None => Ok(None),
+        }
+    }
+
+    fn complete(&mut self, head: MessageHead<ResponseStartLine>, body: Body) -> HttpResponse {
+        // Interim responses do not answer the request
+        if !head.start_line.response_code.is_informational() {
+            self.request_method = None;
+        }
+        HttpResponse {
+            start_line: head.start_line,
+            headers: head.headers,
+            body,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_parser {
+    use super::{HttpRequestParser, HttpResponseParser, Leniency, ParseMode, ParserLimits, Status};
+    use crate::http::http::{Body, ParseHttpError};
+    use crate::http::http_message::RequestMethod;
+    use crate::http::status::StatusCode;
+    use crate::Result::Err;
+
+    #[test]
+    fn request_in_pieces_test() {
+        let input = b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhelloGET / HTTP/1.1\r\n";
+        let mut parser = HttpRequestParser::new();
+
+        assert!(matches!(parser.feed(&input[..10]).unwrap(), Status::Partial));
+        assert!(matches!(parser.feed(&input[10..40]).unwrap(), Status::Partial));
+        match parser.feed(&input[40..]).unwrap() {
+            Status::Complete(request, consumed) => {
+                assert!(matches!(request.start_line.method, RequestMethod::POST));
+                assert_eq!(request.start_line.target, "/submit");
+                assert!(matches!(request.body, Body::Data(ref data) if data == b"hello"));
+                assert_eq!(&input[40 + consumed..], b"GET / HTTP/1.1\r\n");
+            }
+            Status::Partial => panic!("request should be complete"),
+        }
+    }
+
+    #[test]
+    fn response_without_body_test() {
+        let input = b"HTTP/1.1 204 No Content\r\nServer: kparser\r\n\r\n";
+        let mut parser = HttpResponseParser::new();
+
+        for byte in &input[..input.len() - 1] {
+            assert!(matches!(parser.feed(&[*byte]).unwrap(), Status::Partial));
+        }
+        match parser.feed(&input[input.len() - 1..]).unwrap() {
+            Status::Complete(response, consumed) => {
+                assert_eq!(response.start_line.response_code, 204);
+                assert_eq!(consumed, 1);
+                assert!(matches!(response.body, Body::None));
+            }
+            Status::Partial => panic!("response should be complete"),
+        }
+    }
+
+    #[test]
+    fn pipelined_requests_test() {
+        let input = b"POST /a HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\n\r\nabcPOST /b HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n4;ext=1\r\nwiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\nGET /c HTTP/1.1\r\nHost: a\r\n\r\n";
+        let mut parser = HttpRequestParser::new();
+        let mut rest: &[u8] = input;
+        let mut requests = Vec::new();
+        while !rest.is_empty() {
+            match parser.feed(rest).unwrap() {
+                Status::Complete(request, consumed) => {
+                    requests.push(request);
+                    rest = &rest[consumed..];
+                }
+                Status::Partial => panic!("all requests should be complete"),
+            }
+        }
+
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].start_line.target, "/a");
+        assert!(matches!(requests[0].body, Body::Data(ref data) if data == b"abc"));
+        assert_eq!(requests[1].start_line.target, "/b");
+        match &requests[1].body {
+            Body::Chunked(chunked) => {
+                assert_eq!(chunked.data(), b"wikipedia".to_vec());
+                assert_eq!(chunked.trailers.len(), 1);
+            }
+            _ => panic!("chunked body expected"),
+        }
+        assert_eq!(requests[2].start_line.target, "/c");
+        assert!(matches!(requests[2].body, Body::None));
+
+        let input = b"\r\n\r\n\r\nGET / HTTP/1.1\r\nHost: a\r\n\r\n";
+        match HttpRequestParser::new().feed(input).unwrap() {
+            Status::Complete(request, consumed) => {
+                assert_eq!(consumed, input.len());
+                assert_eq!(request.start_line.target, "/");
+            }
+            Status::Partial => panic!("request should be complete"),
+        }
+        // Also when the empty lines arrive one byte at a time
+        let mut parser = HttpRequestParser::new();
+        for byte in &input[..input.len() - 1] {
+            assert!(matches!(parser.feed(&[*byte]).unwrap(), Status::Partial));
+        }
+        assert!(matches!(parser.feed(&input[input.len() - 1..]).unwrap(), Status::Complete(_, 1)));
+    }
+
+    #[test]
+    fn response_framing_test() {
+        let mut parser = HttpResponseParser::new();
+        parser.set_request_method(RequestMethod::HEAD);
+        let input = b"HTTP/1.1 200 OK\r\nContent-Length: 42\r\n\r\n";
+        assert!(matches!(
+            parser.feed(input).unwrap(),
+            Status::Complete(ref response, consumed)
+                if matches!(response.body, Body::None) && consumed == input.len()
+        ));
+
+        let input = b"HTTP/1.1 200 OK\r\n\r\nuntil the connection closes";
+        assert!(matches!(parser.feed(input).unwrap(), Status::Partial));
+        let response = parser.finish().unwrap().unwrap();
+        assert!(
+            matches!(response.body, Body::Data(ref data) if data == b"until the connection closes")
+        );
+        assert!(parser.finish().unwrap().is_none());
+    }
+
+    #[test]
+    fn parser_limits_test() {
+        let limits = ParserLimits {
+            max_start_line: 16,
+            max_headers: 2,
+            max_header_bytes: 64,
+            max_body_size: Some(4),
+        };
+        let feed = |input: &[u8]| HttpRequestParser::with_limits(limits).feed(input);
+
+        // Rejected before the end of the head arrives
+        let error = match feed(b"GET /a/very/long/path") {
+            Err(error) => error,
+            _ => panic!("start line should be too long"),
+        };
+        assert_eq!(error, ParseHttpError::StartLineTooLong(16));
+        assert_eq!(error.status_code(), StatusCode::URI_TOO_LONG);
+
+        let input = b"GET / HTTP/1.1\r\nHost: a\r\nA: 1\r\nB: 2\r\n\r\n";
+        assert!(matches!(feed(input), Err(ParseHttpError::TooManyHeaders(2))));
+        let input = b"GET / HTTP/1.1\r\nHost: a\r\nCookie: 0123456789abcdef0123456789abcdef0123456789abcdef0123456789";
+        let error = match feed(input) {
+            Err(error) => error,
+            _ => panic!("headers should be too large"),
+        };
+        assert_eq!(error.status_code(), StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 5\r\n\r\n";
+        let error = match feed(input) {
+            Err(error) => error,
+            _ => panic!("body should be too large"),
+        };
+        assert_eq!(error, ParseHttpError::BodyTooLarge(4));
+        assert_eq!(error.status_code(), StatusCode::CONTENT_TOO_LARGE);
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\n";
+        assert!(matches!(feed(input), Err(ParseHttpError::BodyTooLarge(4))));
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 4\r\n\r\nabcd";
+        assert!(matches!(feed(input).unwrap(), Status::Complete(..)));
+
+        let mut parser = HttpResponseParser::with_limits(limits);
+        let input = b"HTTP/1.1 200 OK\r\n\r\nuntil close";
+        assert!(matches!(parser.feed(input), Err(ParseHttpError::BodyTooLarge(4))));
+
+        // Without a body limit the length still has to fit after the head
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 18446744073709551615\r\n\r\n";
+        let result = HttpRequestParser::new().feed(input);
+        assert!(matches!(result, Err(ParseHttpError::ParseHeaderError(_))));
+    }
+
+    #[test]
+    fn strict_and_lenient_mode_test() {
+        let cases: [(&[u8], Leniency); 5] = [
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+                Leniency::ContentLengthWithTransferEncoding,
+            ),
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\nContent-Length: 3\r\n\r\nabc",
+                Leniency::DuplicateContentLength,
+            ),
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length : 3\r\n\r\nabc",
+                Leniency::WhitespaceBeforeColon,
+            ),
+            (
+                b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length:\r\n 3\r\n\r\nabc",
+                Leniency::ObsFold,
+            ),
+            (
+                b"POST / HTTP/1.1\nHost: a\nContent-Length: 3\n\nabc",
+                Leniency::BareLf,
+            ),
+        ];
+        for (input, leniency) in cases {
+            assert!(matches!(HttpRequestParser::new().feed(input), Err(_)));
+
+            let mut parser = HttpRequestParser::new().with_mode(ParseMode::Lenient);
+            match parser.feed(input).unwrap() {
+                Status::Complete(request, consumed) => {
+                    assert_eq!(consumed, input.len());
+                    assert!(request.headers.len() <= 3);
+                    let data = match request.body {
+                        Body::Data(data) => data,
+                        Body::Chunked(chunked) => chunked.data(),
+                        _ => panic!("body expected"),
+                    };
+                    assert_eq!(data, b"abc");
+                }
+                Status::Partial => panic!("request should be complete"),
+            }
+            assert_eq!(parser.tolerated(), &[leniency]);
+        }
+
+        let input = b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3, 4\r\n\r\nabc";
+        let mut parser = HttpRequestParser::new().with_mode(ParseMode::Lenient);
+        assert!(matches!(parser.feed(input), Err(_)));
+    }
+}
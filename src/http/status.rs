@@ -0,0 +1,177 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::http::ParseHttpError;
+
+/// An HTTP status code (RFC 9110 section 15). Any three-digit code is
+/// accepted; unregistered codes simply have no canonical reason phrase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+macro_rules! define_status_codes {
+    ($($name:ident => ($code:expr, $reason:expr)),* $(,)?) => {
+        impl StatusCode {
+            $(pub const $name: StatusCode = StatusCode($code);)*
+
+            /// The reason phrase registered for the code, if any.
+            pub fn canonical_reason(&self) -> Option<&'static str> {
+                match self.0 {
+                    $($code => Some($reason),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+define_status_codes! {
+    CONTINUE => (100, "Continue"),
+    SWITCHING_PROTOCOLS => (101, "Switching Protocols"),
+    PROCESSING => (102, "Processing"),
+    EARLY_HINTS => (103, "Early Hints"),
+    OK => (200, "OK"),
+    CREATED => (201, "Created"),
+    ACCEPTED => (202, "Accepted"),
+    NON_AUTHORITATIVE_INFORMATION => (203, "Non-Authoritative Information"),
+    NO_CONTENT => (204, "No Content"),
+    RESET_CONTENT => (205, "Reset Content"),
+    PARTIAL_CONTENT => (206, "Partial Content"),
+    MULTI_STATUS => (207, "Multi-Status"),
+    ALREADY_REPORTED => (208, "Already Reported"),
+    IM_USED => (226, "IM Used"),
+    MULTIPLE_CHOICES => (300, "Multiple Choices"),
+    MOVED_PERMANENTLY => (301, "Moved Permanently"),
+    FOUND => (302, "Found"),
+    SEE_OTHER => (303, "See Other"),
+    NOT_MODIFIED => (304, "Not Modified"),
+    USE_PROXY => (305, "Use Proxy"),
+    TEMPORARY_REDIRECT => (307, "Temporary Redirect"),
+    PERMANENT_REDIRECT => (308, "Permanent Redirect"),
+    BAD_REQUEST => (400, "Bad Request"),
+    UNAUTHORIZED => (401, "Unauthorized"),
+    PAYMENT_REQUIRED => (402, "Payment Required"),
+    FORBIDDEN => (403, "Forbidden"),
+    NOT_FOUND => (404, "Not Found"),
+    METHOD_NOT_ALLOWED => (405, "Method Not Allowed"),
+    NOT_ACCEPTABLE => (406, "Not Acceptable"),
+    PROXY_AUTHENTICATION_REQUIRED => (407, "Proxy Authentication Required"),
+    REQUEST_TIMEOUT => (408, "Request Timeout"),
+    CONFLICT => (409, "Conflict"),
+    GONE => (410, "Gone"),
+    LENGTH_REQUIRED => (411, "Length Required"),
+    PRECONDITION_FAILED => (412, "Precondition Failed"),
+    CONTENT_TOO_LARGE => (413, "Content Too Large"),
+    URI_TOO_LONG => (414, "URI Too Long"),
+    UNSUPPORTED_MEDIA_TYPE => (415, "Unsupported Media Type"),
+    RANGE_NOT_SATISFIABLE => (416, "Range Not Satisfiable"),
+    EXPECTATION_FAILED => (417, "Expectation Failed"),
+    IM_A_TEAPOT => (418, "I'm a teapot"),
+    MISDIRECTED_REQUEST => (421, "Misdirected Request"),
+    UNPROCESSABLE_CONTENT => (422, "Unprocessable Content"),
+    LOCKED => (423, "Locked"),
+    FAILED_DEPENDENCY => (424, "Failed Dependency"),
+    TOO_EARLY => (425, "Too Early"),
+    UPGRADE_REQUIRED => (426, "Upgrade Required"),
+    PRECONDITION_REQUIRED => (428, "Precondition Required"),
+    TOO_MANY_REQUESTS => (429, "Too Many Requests"),
+    REQUEST_HEADER_FIELDS_TOO_LARGE => (431, "Request Header Fields Too Large"),
+    UNAVAILABLE_FOR_LEGAL_REASONS => (451, "Unavailable For Legal Reasons"),
+    INTERNAL_SERVER_ERROR => (500, "Internal Server Error"),
+    NOT_IMPLEMENTED => (501, "Not Implemented"),
+    BAD_GATEWAY => (502, "Bad Gateway"),
+    SERVICE_UNAVAILABLE => (503, "Service Unavailable"),
+    GATEWAY_TIMEOUT => (504, "Gateway Timeout"),
+    HTTP_VERSION_NOT_SUPPORTED => (505, "HTTP Version Not Supported"),
+    VARIANT_ALSO_NEGOTIATES => (506, "Variant Also Negotiates"),
+    INSUFFICIENT_STORAGE => (507, "Insufficient Storage"),
+    LOOP_DETECTED => (508, "Loop Detected"),
+    NOT_EXTENDED => (510, "Not Extended"),
+    NETWORK_AUTHENTICATION_REQUIRED => (511, "Network Authentication Required"),
+}
+
+impl StatusCode {
+    pub fn from_u16(code: u16) -> Result<StatusCode, ParseHttpError> {
+        if !(100..1000).contains(&code) {
+            return Err(ParseHttpError::ParseError(format!(
+                "Invalid status code: {}",
+                code
+            )));
+        }
+        Ok(StatusCode(code))
+    }
+
+    /// Parses exactly three ASCII digits.
+    pub fn parse(value: &[u8]) -> Result<StatusCode, ParseHttpError> {
+        if value.len() != 3 || !value.iter().all(u8::is_ascii_digit) {
+            return Err(ParseHttpError::ParseError(format!(
+                "Invalid status code: {}",
+                String::from_utf8_lossy(value)
+            )));
+        }
+        let code = value
+            .iter()
+            .fold(0, |code, digit| code * 10 + (digit - b'0') as u16);
+        StatusCode::from_u16(code)
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+}
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+impl From<StatusCode> for u16 {
+    fn from(code: StatusCode) -> Self {
+        code.0
+    }
+}
+
+#[cfg(test)]
+mod test_status {
+    use super::StatusCode;
+    use crate::Result::Err;
+
+    #[test]
+    fn status_code_test() {
+        let code = StatusCode::parse(b"404").unwrap();
+        assert_eq!(code, StatusCode::NOT_FOUND);
+        assert_eq!(code.canonical_reason(), Some("Not Found"));
+        assert!(code.is_client_error());
+        assert!(StatusCode::PERMANENT_REDIRECT.is_redirect());
+        assert!(StatusCode::from_u16(299).unwrap().is_success());
+        assert_eq!(StatusCode::from_u16(299).unwrap().canonical_reason(), None);
+
+        assert!(matches!(StatusCode::parse(b"20"), Err(_)));
+        assert!(matches!(StatusCode::parse(b"abc"), Err(_)));
+        assert!(matches!(StatusCode::parse(b"099"), Err(_)));
+        assert!(matches!(StatusCode::from_u16(1000), Err(_)));
+    }
+}
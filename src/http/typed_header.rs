@@ -0,0 +1,976 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::date::HttpDate;
+use super::http::{HeaderKey, HeaderValue, ParseHttpError, StandardHeaders};
+use super::media_type::{is_ows, is_token, parse_parameters, MediaType};
+use super::parser::parse_content_length;
+
+/// A header field with a parsed representation. `decode` sees the values
+/// of every field line with the header's name, in order, so list-valued
+/// headers split across several lines are read as a single list.
+pub trait TypedHeader: Clone {
+    fn name() -> HeaderKey;
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError>;
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError>;
+}
+
+/// The value of a header that must occur exactly once.
+fn single_value<'v>(
+    name: &HeaderKey,
+    mut values: impl Iterator<Item = &'v str>,
+) -> Result<&'v str, ParseHttpError> {
+    match (values.next(), values.next()) {
+        (Some(value), None) => Ok(value.trim_matches(is_ows)),
+        (None, _) => Err(ParseHttpError::ParseHeaderError(format!("Missing {}", name))),
+        (Some(_), Some(_)) => Err(ParseHttpError::ParseHeaderError(format!(
+            "Repeated {}",
+            name
+        ))),
+    }
+}
+
+/// Splits comma-separated lists (RFC 9110 section 5.6.1) across all field
+/// lines, ignoring empty elements and commas inside quoted strings.
+pub(crate) fn list_elements<'v>(values: impl Iterator<Item = &'v str>) -> Vec<&'v str> {
+    let mut elements = Vec::new();
+    for value in values {
+        let mut start = 0;
+        let mut quoted = false;
+        let mut escaped = false;
+        for (index, byte) in value.bytes().enumerate() {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' if quoted => escaped = true,
+                b'"' => quoted = !quoted,
+                b',' if !quoted => {
+                    elements.push(&value[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        elements.push(&value[start..]);
+    }
+    elements
+        .into_iter()
+        .map(|element| element.trim_matches(is_ows))
+        .filter(|element| !element.is_empty())
+        .collect()
+}
+
+/// A list of tokens such as Connection options, rejecting anything else.
+fn token_list<'v>(
+    name: &HeaderKey,
+    values: impl Iterator<Item = &'v str>,
+) -> Result<Vec<String>, ParseHttpError> {
+    let mut tokens = Vec::new();
+    for element in list_elements(values) {
+        if !is_token(element) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid {}: {}",
+                name, element
+            )));
+        }
+        tokens.push(element.to_string());
+    }
+    Ok(tokens)
+}
+
+fn list_value(elements: &[String]) -> Result<HeaderValue, ParseHttpError> {
+    HeaderValue::new(elements.join(", "))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+impl TypedHeader for ContentLength {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Content_Length)
+    }
+
+    /// Repeated fields are accepted as long as every value is the same.
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        match parse_content_length(values)? {
+            Some(length) => Ok(ContentLength(length as u64)),
+            None => Err(ParseHttpError::ParseHeaderError(
+                "Missing Content-Length".to_string(),
+            )),
+        }
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        HeaderValue::new(self.0.to_string())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentType(pub MediaType);
+impl TypedHeader for ContentType {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Content_Type)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let value = single_value(&Self::name(), values)?;
+        Ok(ContentType(MediaType::parse(value)?))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        HeaderValue::new(self.0.to_string())
+    }
+}
+
+/// The Host header: `uri-host [ ":" port ]` (RFC 9110 section 7.2). IPv6
+/// literals keep their brackets. The host may be empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Host {
+    pub host: String,
+    pub port: Option<u16>,
+}
+impl Host {
+    pub fn new(host: &str, port: Option<u16>) -> Host {
+        Host {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Host, ParseHttpError> {
+        let invalid = || ParseHttpError::ParseHeaderError(format!("Invalid Host: {}", value));
+        let (host, port) = if value.starts_with('[') {
+            let Some(end) = value.find(']') else {
+                return Err(invalid());
+            };
+            let literal = &value[1..end];
+            if literal.is_empty()
+                || !literal
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b":.%".contains(&b))
+            {
+                return Err(invalid());
+            }
+            match &value[end + 1..] {
+                "" => (&value[..end + 1], None),
+                rest => match rest.strip_prefix(':') {
+                    Some(port) => (&value[..end + 1], Some(port)),
+                    None => return Err(invalid()),
+                },
+            }
+        } else {
+            match value.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (value, None),
+            }
+        };
+        // reg-name or IPv4address (RFC 3986 section 3.2.2)
+        if !host.starts_with('[')
+            && !host
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=%".contains(&b))
+        {
+            return Err(invalid());
+        }
+        let port = match port {
+            // An empty port is allowed and means the default one
+            None | Some("") => None,
+            Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => match port.parse() {
+                std::result::Result::Ok(port) => Some(port),
+                std::result::Result::Err(_) => return Err(invalid()),
+            },
+            Some(_) => return Err(invalid()),
+        };
+        Ok(Host::new(host, port))
+    }
+}
+impl Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+impl TypedHeader for Host {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Host)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        Host::parse(single_value(&Self::name(), values)?)
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        HeaderValue::new(self.to_string())
+    }
+}
+
+macro_rules! define_date_headers {
+    ($($(#[$doc:meta])* $name:ident => $header:ident),* $(,)?) => {
+        $(
+            $(#[$doc])*
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct $name(pub HttpDate);
+            impl TypedHeader for $name {
+                fn name() -> HeaderKey {
+                    HeaderKey::from(StandardHeaders::$header)
+                }
+
+                fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+                    Ok($name(HttpDate::parse(single_value(&Self::name(), values)?)?))
+                }
+
+                fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+                    HeaderValue::new(self.0.to_string())
+                }
+            }
+        )*
+    };
+}
+
+define_date_headers! {
+    Date => Date,
+    IfModifiedSince => If_Modified_Since,
+    IfUnmodifiedSince => If_Unmodified_Since,
+}
+
+/// Connection options (RFC 9110 section 7.6.1). Options compare without
+/// regard to case.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Connection(pub Vec<String>);
+impl Connection {
+    pub fn close() -> Connection {
+        Connection(vec!["close".to_string()])
+    }
+
+    pub fn keep_alive() -> Connection {
+        Connection(vec!["keep-alive".to_string()])
+    }
+
+    pub fn contains(&self, option: &str) -> bool {
+        self.0.iter().any(|value| value.eq_ignore_ascii_case(option))
+    }
+
+    pub fn is_close(&self) -> bool {
+        self.contains("close")
+    }
+}
+impl TypedHeader for Connection {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Connection)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        Ok(Connection(token_list(&Self::name(), values)?))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        list_value(&self.0)
+    }
+}
+
+/// Protocols offered for an upgrade, as `name[/version]`, in order of
+/// preference (RFC 9110 section 7.8).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Upgrade(pub Vec<String>);
+impl Upgrade {
+    pub fn websocket() -> Upgrade {
+        Upgrade(vec!["websocket".to_string()])
+    }
+
+    /// Whether a protocol with this name is offered, in any version.
+    pub fn contains(&self, protocol: &str) -> bool {
+        self.0.iter().any(|value| {
+            let name = value.split_once('/').map(|(name, _)| name).unwrap_or(value);
+            name.eq_ignore_ascii_case(protocol)
+        })
+    }
+}
+impl TypedHeader for Upgrade {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Upgrade)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let mut protocols = Vec::new();
+        for element in list_elements(values) {
+            let valid = match element.split_once('/') {
+                Some((name, version)) => is_token(name) && is_token(version),
+                None => is_token(element),
+            };
+            if !valid {
+                return Err(ParseHttpError::ParseHeaderError(format!(
+                    "Invalid Upgrade: {}",
+                    element
+                )));
+            }
+            protocols.push(element.to_string());
+        }
+        Ok(Upgrade(protocols))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        list_value(&self.0)
+    }
+}
+
+/// Transfer codings in the order they were applied (RFC 9112 section 6.1).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferEncoding(pub Vec<String>);
+impl TransferEncoding {
+    pub fn chunked() -> TransferEncoding {
+        TransferEncoding(vec!["chunked".to_string()])
+    }
+
+    /// Whether `chunked` is the final coding.
+    pub fn is_chunked(&self) -> bool {
+        self.0
+            .last()
+            .is_some_and(|coding| coding.eq_ignore_ascii_case("chunked"))
+    }
+}
+impl TypedHeader for TransferEncoding {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Transfer_Encoding)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        Ok(TransferEncoding(token_list(&Self::name(), values)?))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        list_value(&self.0)
+    }
+}
+
+/// A weight between 0 and 1 with at most three decimals (RFC 9110 section
+/// 12.4.2), kept in thousandths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quality(u16);
+impl Quality {
+    pub const ZERO: Quality = Quality(0);
+    pub const ONE: Quality = Quality(1000);
+
+    pub fn from_thousandths(value: u16) -> Result<Quality, ParseHttpError> {
+        if value > 1000 {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid quality: {}",
+                value
+            )));
+        }
+        Ok(Quality(value))
+    }
+
+    pub fn as_thousandths(&self) -> u16 {
+        self.0
+    }
+
+    /// Parses `qvalue = ( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )`.
+    pub fn parse(value: &str) -> Result<Quality, ParseHttpError> {
+        let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+        if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid quality: {}",
+                value
+            )));
+        }
+        let thousandths = fraction
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(3)
+            .fold(0, |total, digit| total * 10 + (digit - b'0') as u16);
+        match whole {
+            "0" => Ok(Quality(thousandths)),
+            "1" if thousandths == 0 => Ok(Quality::ONE),
+            _ => Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid quality: {}",
+                value
+            ))),
+        }
+    }
+}
+impl Default for Quality {
+    fn default() -> Self {
+        Quality::ONE
+    }
+}
+impl Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            1000 => f.write_str("1"),
+            0 => f.write_str("0"),
+            value => {
+                let digits = format!("{:03}", value);
+                write!(f, "0.{}", digits.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+/// A list element with its `q` weight, 1 when none was given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QualityItem<T> {
+    pub item: T,
+    pub quality: Quality,
+}
+impl<T> QualityItem<T> {
+    pub fn new(item: T, quality: Quality) -> QualityItem<T> {
+        QualityItem { item, quality }
+    }
+}
+impl<T: Display> Display for QualityItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.item)?;
+        if self.quality != Quality::ONE {
+            write!(f, ";q={}", self.quality)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+
+/// Splits `element;q=weight` into the element and its weight. Other
+/// parameters are ignored.
+fn parse_weighted(element: &str) -> Result<(&str, Quality), ParseHttpError> {
+    let (item, rest) = match element.find(';') {
+        Some(index) => (element[..index].trim_matches(is_ows), &element[index..]),
+        None => (element, ""),
+    };
+    let mut quality = Quality::ONE;
+    for (name, value) in parse_parameters(rest, element)? {
+        if name == "q" {
+            quality = Quality::parse(&value)?;
+        }
+    }
+    Ok((item, quality))
+}
+
+/// Content codings the client accepts (RFC 9110 section 12.5.3). An empty
+/// list means only `identity` is acceptable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptEncoding(pub Vec<QualityItem<String>>);
+impl AcceptEncoding {
+    /// The weight given to `coding`, falling back to `*`. `identity` is
+    /// acceptable unless excluded explicitly or through `*;q=0`.
+    pub fn quality(&self, coding: &str) -> Quality {
+        let find = |name: &str| {
+            self.0
+                .iter()
+                .find(|item| item.item.eq_ignore_ascii_case(name))
+                .map(|item| item.quality)
+        };
+        match (find(coding), find("*")) {
+            (Some(quality), _) | (None, Some(quality)) => quality,
+            (None, None) if coding.eq_ignore_ascii_case("identity") => Quality::ONE,
+            (None, None) => Quality::ZERO,
+        }
+    }
+}
+impl TypedHeader for AcceptEncoding {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Accept_Encoding)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let mut codings = Vec::new();
+        for element in list_elements(values) {
+            let (coding, quality) = parse_weighted(element)?;
+            if !is_token(coding) {
+                return Err(ParseHttpError::ParseHeaderError(format!(
+                    "Invalid Accept-Encoding: {}",
+                    element
+                )));
+            }
+            codings.push(QualityItem::new(coding.to_string(), quality));
+        }
+        Ok(AcceptEncoding(codings))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        let elements: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+        list_value(&elements)
+    }
+}
+
+impl AcceptEncoding {
+    pub fn negotiate<'o, S: AsRef<str>>(&self, offers: &'o [S]) -> Option<&'o S> {
+        best_offer(offers, |offer| self.quality(offer.as_ref()))
+    }
+}
+
+/// The offer with the highest non-zero weight, the earliest one on ties.
+fn best_offer<T>(offers: &[T], quality: impl Fn(&T) -> Quality) -> Option<&T> {
+    let mut best: Option<(&T, Quality)> = None;
+    for offer in offers {
+        let offer_quality = quality(offer);
+        if offer_quality > Quality::ZERO
+            && best.is_none_or(|(_, best_quality)| offer_quality > best_quality)
+        {
+            best = Some((offer, offer_quality));
+        }
+    }
+    best.map(|(offer, _)| offer)
+}
+
+/// Media ranges the client accepts (RFC 9110 section 12.5.1). Parameters
+/// before `q` belong to the range, anything after it is ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Accept(pub Vec<QualityItem<MediaType>>);
+impl Accept {
+    /// The weight of the most specific range matching `media_type`, where
+    /// `text/html;level=1` beats `text/html`, which beats `text/*` and
+    /// `*/*`.
+    pub fn quality(&self, media_type: &MediaType) -> Quality {
+        self.0
+            .iter()
+            .filter(|item| media_range_matches(&item.item, media_type))
+            .max_by_key(|item| {
+                (
+                    item.item.type_() != "*",
+                    item.item.subtype() != "*",
+                    item.item.parameters().len(),
+                )
+            })
+            .map_or(Quality::ZERO, |item| item.quality)
+    }
+
+    pub fn negotiate<'o>(&self, offers: &'o [MediaType]) -> Option<&'o MediaType> {
+        best_offer(offers, |offer| self.quality(offer))
+    }
+}
+impl TypedHeader for Accept {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Accept)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let mut ranges = Vec::new();
+        for element in list_elements(values) {
+            let parsed = MediaType::parse(element)?;
+            if parsed.type_() == "*" && parsed.subtype() != "*" {
+                return Err(ParseHttpError::ParseHeaderError(format!(
+                    "Invalid Accept: {}",
+                    element
+                )));
+            }
+            let mut range = MediaType::new(parsed.type_(), parsed.subtype())?;
+            let mut quality = Quality::ONE;
+            for (name, value) in parsed.parameters() {
+                if name == "q" {
+                    quality = Quality::parse(value)?;
+                    break;
+                }
+                range.set_param(name, value)?;
+            }
+            ranges.push(QualityItem::new(range, quality));
+        }
+        Ok(Accept(ranges))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        let elements: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+        list_value(&elements)
+    }
+}
+
+fn media_range_matches(range: &MediaType, media_type: &MediaType) -> bool {
+    (range.type_() == "*" || range.type_() == media_type.type_())
+        && (range.subtype() == "*" || range.subtype() == media_type.subtype())
+        && range.parameters().iter().all(|(name, value)| {
+            media_type
+                .param(name)
+                .is_some_and(|offered| offered.eq_ignore_ascii_case(value))
+        })
+}
+
+/// Natural languages the client prefers (RFC 9110 section 12.5.4), as
+/// language ranges such as `en-GB` or `*`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptLanguage(pub Vec<QualityItem<String>>);
+impl AcceptLanguage {
+    /// The weight of the longest range that equals `tag` or is a prefix of
+    /// it ending at a `-`, so `en` matches `en-US` (RFC 4647 section 3.3.1).
+    pub fn quality(&self, tag: &str) -> Quality {
+        self.0
+            .iter()
+            .filter(|item| language_range_matches(&item.item, tag))
+            .max_by_key(|item| if item.item == "*" { 0 } else { item.item.len() })
+            .map_or(Quality::ZERO, |item| item.quality)
+    }
+
+    pub fn negotiate<'o, S: AsRef<str>>(&self, offers: &'o [S]) -> Option<&'o S> {
+        best_offer(offers, |offer| self.quality(offer.as_ref()))
+    }
+}
+impl TypedHeader for AcceptLanguage {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Accept_Language)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let ranges = weighted_list(&Self::name(), values, is_language_range)?;
+        Ok(AcceptLanguage(ranges))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        let elements: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+        list_value(&elements)
+    }
+}
+
+/// `language-range = ( 1*8ALPHA *( "-" 1*8alphanum ) ) / "*"`
+fn is_language_range(range: &str) -> bool {
+    range == "*"
+        || range.split('-').enumerate().all(|(index, subtag)| {
+            (1..=8).contains(&subtag.len())
+                && subtag.bytes().all(|b| {
+                    if index == 0 {
+                        b.is_ascii_alphabetic()
+                    } else {
+                        b.is_ascii_alphanumeric()
+                    }
+                })
+        })
+}
+
+fn language_range_matches(range: &str, tag: &str) -> bool {
+    range == "*"
+        || tag.len() >= range.len()
+            && tag.as_bytes()[..range.len()].eq_ignore_ascii_case(range.as_bytes())
+            && (tag.len() == range.len() || tag.as_bytes()[range.len()] == b'-')
+}
+
+/// Charsets the client accepts (RFC 9110 section 12.5.2). Charsets not
+/// listed are unacceptable unless `*` is present.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AcceptCharset(pub Vec<QualityItem<String>>);
+impl AcceptCharset {
+    pub fn quality(&self, charset: &str) -> Quality {
+        let find = |name: &str| {
+            self.0
+                .iter()
+                .find(|item| item.item.eq_ignore_ascii_case(name))
+                .map(|item| item.quality)
+        };
+        find(charset).or_else(|| find("*")).unwrap_or(Quality::ZERO)
+    }
+
+    pub fn negotiate<'o, S: AsRef<str>>(&self, offers: &'o [S]) -> Option<&'o S> {
+        best_offer(offers, |offer| self.quality(offer.as_ref()))
+    }
+}
+impl TypedHeader for AcceptCharset {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Accept_Charset)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let charsets = weighted_list(&Self::name(), values, is_token)?;
+        Ok(AcceptCharset(charsets))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        let elements: Vec<String> = self.0.iter().map(|item| item.to_string()).collect();
+        list_value(&elements)
+    }
+}
+
+/// A non-empty list of weighted elements, each checked with `is_valid`.
+fn weighted_list<'v>(
+    name: &HeaderKey,
+    values: impl Iterator<Item = &'v str>,
+    is_valid: fn(&str) -> bool,
+) -> Result<Vec<QualityItem<String>>, ParseHttpError> {
+    let mut items = Vec::new();
+    for element in list_elements(values) {
+        let (item, quality) = parse_weighted(element)?;
+        if !is_valid(item) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid {}: {}",
+                name, element
+            )));
+        }
+        items.push(QualityItem::new(item.to_string(), quality));
+    }
+    if items.is_empty() {
+        return Err(ParseHttpError::ParseHeaderError(format!("Empty {}", name)));
+    }
+    Ok(items)
+}
+
+/// An entity tag (RFC 9110 section 8.8.3), without its quotes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityTag {
+    pub weak: bool,
+    pub tag: String,
+}
+impl EntityTag {
+    pub fn strong(tag: &str) -> EntityTag {
+        EntityTag {
+            weak: false,
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn weak(tag: &str) -> EntityTag {
+        EntityTag {
+            weak: true,
+            tag: tag.to_string(),
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<EntityTag, ParseHttpError> {
+        let (weak, opaque) = match value.strip_prefix("W/") {
+            Some(opaque) => (true, opaque),
+            None => (false, value),
+        };
+        let tag = opaque
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+            .filter(|tag| tag.bytes().all(is_etag_char));
+        match tag {
+            Some(tag) => Ok(EntityTag {
+                weak,
+                tag: tag.to_string(),
+            }),
+            None => Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid entity tag: {}",
+                value
+            ))),
+        }
+    }
+
+    /// Both tags are strong and identical.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// The tags are identical, whether weak or not.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+// etagc = %x21 / %x23-7E / obs-text
+fn is_etag_char(byte: u8) -> bool {
+    byte == 0x21 || (0x23..=0x7e).contains(&byte) || byte >= 0x80
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ETag(pub EntityTag);
+impl TypedHeader for ETag {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::ETag)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        Ok(ETag(EntityTag::parse(single_value(&Self::name(), values)?)?))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        HeaderValue::new(self.0.to_string())
+    }
+}
+
+/// `*` or a list of entity tags (RFC 9110 section 13.1.2).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+impl IfNoneMatch {
+    /// Whether the condition fails for a representation with `etag`, in
+    /// which case a GET or HEAD is answered with 304. Uses weak comparison.
+    pub fn matches(&self, etag: &EntityTag) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        }
+    }
+}
+impl TypedHeader for IfNoneMatch {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::If_None_Match)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let elements = list_elements(values);
+        if elements == ["*"] {
+            return Ok(IfNoneMatch::Any);
+        }
+        let mut tags = Vec::new();
+        for element in elements {
+            tags.push(EntityTag::parse(element)?);
+        }
+        Ok(IfNoneMatch::Tags(tags))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        match self {
+            IfNoneMatch::Any => HeaderValue::new("*".to_string()),
+            IfNoneMatch::Tags(tags) => {
+                let elements: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+                list_value(&elements)
+            }
+        }
+    }
+}
+
+/// The Location header, a URI reference that may be relative to the
+/// request's target (RFC 9110 section 10.2.2).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location(pub String);
+impl TypedHeader for Location {
+    fn name() -> HeaderKey {
+        HeaderKey::from(StandardHeaders::Location)
+    }
+
+    fn decode<'v>(values: impl Iterator<Item = &'v str>) -> Result<Self, ParseHttpError> {
+        let value = single_value(&Self::name(), values)?;
+        if value.is_empty() || value.bytes().any(|b| b <= b' ' || b >= 0x7f) {
+            return Err(ParseHttpError::ParseHeaderError(format!(
+                "Invalid Location: {}",
+                value
+            )));
+        }
+        Ok(Location(value.to_string()))
+    }
+
+    fn encode(&self) -> Result<HeaderValue, ParseHttpError> {
+        HeaderValue::new(self.0.clone())
+    }
+}
+
+#[cfg(test)]
+mod test_typed_header {
+    use super::{
+        Accept, AcceptCharset, AcceptEncoding, AcceptLanguage, Connection, ContentLength,
+        ContentType, Date, ETag, EntityTag, Host, IfNoneMatch, Location, Quality,
+        TransferEncoding, TypedHeader, Upgrade,
+    };
+    use crate::http::date::HttpDate;
+    use crate::http::header_map::HeaderMap;
+    use crate::http::http::{HeaderKey, HeaderValue};
+    use crate::http::media_type::MediaType;
+    use crate::Result::Err;
+
+    fn decode<H: TypedHeader>(values: &[&str]) -> crate::Result<H, crate::http::http::ParseHttpError> {
+        H::decode(values.iter().copied())
+    }
+
+    #[test]
+    fn typed_header_decode_test() {
+        assert_eq!(decode::<ContentLength>(&["42", "42"]).unwrap().0, 42);
+        assert!(matches!(decode::<ContentLength>(&["42", "43"]), Err(_)));
+        assert!(decode::<ContentType>(&["text/html; charset=utf-8"]).unwrap().0.is("text", "html"));
+        assert!(matches!(decode::<ContentType>(&["text/html", "text/plain"]), Err(_)));
+
+        assert_eq!(decode::<Host>(&["example.com:8080"]).unwrap(), Host::new("example.com", Some(8080)));
+        assert_eq!(decode::<Host>(&["[::1]"]).unwrap(), Host::new("[::1]", None));
+        assert_eq!(decode::<Host>(&["[::1]:443"]).unwrap().to_string(), "[::1]:443");
+        assert!(matches!(decode::<Host>(&["example.com:http"]), Err(_)));
+        assert!(matches!(decode::<Host>(&["exa mple.com"]), Err(_)));
+        assert!(matches!(decode::<Host>(&["a.com", "b.com"]), Err(_)));
+
+        let connection = decode::<Connection>(&["Upgrade", " ,keep-alive"]).unwrap();
+        assert_eq!(connection.0, vec!["Upgrade", "keep-alive"]);
+        assert!(connection.contains("upgrade") && !connection.is_close());
+        assert!(decode::<Upgrade>(&["HTTP/2.0, WebSocket"]).unwrap().contains("websocket"));
+        assert!(matches!(decode::<Upgrade>(&["a/b/c"]), Err(_)));
+        assert!(decode::<TransferEncoding>(&["gzip", "chunked"]).unwrap().is_chunked());
+
+        let accept = decode::<AcceptEncoding>(&["gzip;q=0.5, br", "*;q=0"]).unwrap();
+        assert_eq!(accept.quality("br"), Quality::ONE);
+        assert_eq!(accept.quality("GZIP").as_thousandths(), 500);
+        assert_eq!(accept.quality("identity"), Quality::ZERO);
+        assert_eq!(decode::<AcceptEncoding>(&[""]).unwrap().quality("identity"), Quality::ONE);
+        assert!(matches!(decode::<AcceptEncoding>(&["gzip;q=1.5"]), Err(_)));
+        assert!(matches!(decode::<AcceptEncoding>(&["gzip;q=0.0001"]), Err(_)));
+
+        let etag = decode::<ETag>(&["W/\"abc\""]).unwrap().0;
+        assert_eq!(etag, EntityTag::weak("abc"));
+        assert!(!etag.strong_eq(&EntityTag::strong("abc")) && etag.weak_eq(&EntityTag::strong("abc")));
+        assert!(matches!(decode::<ETag>(&["abc"]), Err(_)));
+        let if_none_match = decode::<IfNoneMatch>(&["\"a,b\", W/\"c\""]).unwrap();
+        assert_eq!(if_none_match, IfNoneMatch::Tags(vec![EntityTag::strong("a,b"), EntityTag::weak("c")]));
+        assert!(if_none_match.matches(&EntityTag::strong("c")));
+        assert!(decode::<IfNoneMatch>(&["*"]).unwrap().matches(&EntityTag::strong("x")));
+
+        assert_eq!(decode::<Location>(&["/next?page=2"]).unwrap().0, "/next?page=2");
+        assert_eq!(
+            decode::<Date>(&["Sun, 06 Nov 1994 08:49:37 GMT"]).unwrap().0,
+            HttpDate::from_unix_secs(784111777)
+        );
+    }
+
+    #[test]
+    fn typed_header_map_test() {
+        let mut headers = HeaderMap::new();
+        assert!(headers.typed_get::<ContentLength>().is_none());
+        headers.append(
+            HeaderKey::new("content-length".to_string()).unwrap(),
+            HeaderValue::new("10".to_string()).unwrap(),
+        );
+        headers.typed_insert(ContentLength(5)).unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.typed_get::<ContentLength>().unwrap().unwrap().0, 5);
+
+        let media_type = MediaType::parse("application/json").unwrap();
+        headers.typed_insert(ContentType(media_type)).unwrap();
+        headers.typed_insert(AcceptEncoding(vec![])).unwrap();
+        headers.typed_insert(ETag(EntityTag::strong("v1"))).unwrap();
+        headers.typed_insert(Location("https://example.com/".to_string())).unwrap();
+        assert_eq!(
+            String::from_utf8(headers.encode(Default::default())).unwrap(),
+            "content-length: 5\r\nContent-Type: application/json\r\nAccept-Encoding: \r\nETag: \"v1\"\r\nLocation: https://example.com/\r\n"
+        );
+        assert!(matches!(headers.typed_insert(Location("bad\r\nvalue".to_string())), Err(_)));
+    }
+
+    #[test]
+    fn content_negotiation_test() {
+        let accept =
+            decode::<Accept>(&["text/*;q=0.3, text/html;q=0.7, text/html;level=1", "*/*;q=0.5"])
+                .unwrap();
+        let media_type = |value: &str| MediaType::parse(value).unwrap();
+        assert_eq!(accept.quality(&media_type("text/html;level=1")), Quality::ONE);
+        assert_eq!(accept.quality(&media_type("text/html;level=2")).as_thousandths(), 700);
+        assert_eq!(accept.quality(&media_type("text/plain")).as_thousandths(), 300);
+        assert_eq!(accept.quality(&media_type("image/png")).as_thousandths(), 500);
+        let offers = [media_type("image/png"), media_type("text/plain"), media_type("text/html")];
+        assert_eq!(accept.negotiate(&offers), Some(&offers[2]));
+
+        let accept = decode::<Accept>(&["application/json;q=0;charset=utf-8"]).unwrap();
+        assert_eq!(accept.0[0].item, media_type("application/json"));
+        assert_eq!(accept.negotiate(&[media_type("application/json")]), None);
+        assert!(matches!(decode::<Accept>(&["*/html"]), Err(_)));
+        assert!(matches!(decode::<Accept>(&["text/html;q=2"]), Err(_)));
+
+        let language = decode::<AcceptLanguage>(&["en;q=0.5, en-GB, *;q=0.1"]).unwrap();
+        assert_eq!(language.quality("en-gb"), Quality::ONE);
+        assert_eq!(language.quality("en-US").as_thousandths(), 500);
+        assert_eq!(language.quality("eng").as_thousandths(), 100);
+        assert_eq!(language.negotiate(&["fr", "en-US", "en-GB"]), Some(&"en-GB"));
+        assert_eq!(language.encode().unwrap().as_str(), "en;q=0.5, en-GB, *;q=0.1");
+        assert!(matches!(decode::<AcceptLanguage>(&["en_GB"]), Err(_)));
+        assert!(matches!(decode::<AcceptLanguage>(&[""]), Err(_)));
+
+        let charset = decode::<AcceptCharset>(&["iso-8859-5, UTF-8;q=0.8"]).unwrap();
+        assert_eq!(charset.quality("utf-8").as_thousandths(), 800);
+        assert_eq!(charset.quality("us-ascii"), Quality::ZERO);
+        assert_eq!(charset.negotiate(&["us-ascii".to_string()]), None);
+
+        let encoding = decode::<AcceptEncoding>(&["gzip;q=0.8, br"]).unwrap();
+        assert_eq!(encoding.negotiate(&["gzip", "br", "identity"]), Some(&"br"));
+        assert_eq!(decode::<AcceptEncoding>(&["*;q=0"]).unwrap().negotiate(&["gzip"]), None);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::http::ParseHttpError;
+
+/// The four request-target forms of RFC 9112 section 3.2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetForm {
+    Origin,
+    Absolute,
+    Authority,
+    Asterisk,
+}
+
+/// A parsed request-target. Components are kept exactly as received, so
+/// `to_string()` gives back the original target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestTarget {
+    form: TargetForm,
+    scheme: Option<String>,
+    authority: Option<String>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+impl RequestTarget {
+    pub fn parse(value: &str) -> Result<RequestTarget, ParseHttpError> {
+        if value.is_empty() {
+            return Err(ParseHttpError::ParseError("Empty request target".to_string()));
+        }
+        if let Some(c) = value.chars().find(|c| !c.is_ascii_graphic()) {
+            return Err(ParseHttpError::ParseError(format!(
+                "Invalid character {:?} in request target",
+                c
+            )));
+        }
+
+        if value == "*" {
+            return Ok(RequestTarget {
+                form: TargetForm::Asterisk,
+                scheme: None,
+                authority: None,
+                path: "*".to_string(),
+                query: None,
+                fragment: None,
+            });
+        }
+
+        if value.starts_with('/') {
+            let (path, query, fragment) = split_path(value);
+            return Ok(RequestTarget {
+                form: TargetForm::Origin,
+                scheme: None,
+                authority: None,
+                path: path.to_string(),
+                query: query.map(|query| query.to_string()),
+                fragment: fragment.map(|fragment| fragment.to_string()),
+            });
+        }
+
+        if let Some(scheme_end) = value.find("://") {
+            let scheme = &value[..scheme_end];
+            if !is_valid_scheme(scheme) {
+                return Err(ParseHttpError::ParseError(format!(
+                    "Invalid scheme: {}",
+                    scheme
+                )));
+            }
+            let rest = &value[scheme_end + 3..];
+            let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+            let authority = &rest[..authority_end];
+            validate_authority(authority)?;
+            let (path, query, fragment) = split_path(&rest[authority_end..]);
+            return Ok(RequestTarget {
+                form: TargetForm::Absolute,
+                scheme: Some(scheme.to_string()),
+                authority: Some(authority.to_string()),
+                path: path.to_string(),
+                query: query.map(|query| query.to_string()),
+                fragment: fragment.map(|fragment| fragment.to_string()),
+            });
+        }
+
+        // authority-form is host and port only, used by CONNECT
+        validate_authority(value)?;
+        if value.contains('@') || split_host_port(value).1.is_none() {
+            return Err(ParseHttpError::ParseError(format!(
+                "Invalid authority-form target: {}",
+                value
+            )));
+        }
+        Ok(RequestTarget {
+            form: TargetForm::Authority,
+            scheme: None,
+            authority: Some(value.to_string()),
+            path: String::new(),
+            query: None,
+            fragment: None,
+        })
+    }
+
+    pub fn form(&self) -> TargetForm {
+        self.form
+    }
+
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// The raw authority, including any userinfo and port.
+    pub fn authority(&self) -> Option<&str> {
+        self.authority.as_deref()
+    }
+
+    /// The host without brackets around IPv6 literals.
+    pub fn host(&self) -> Option<&str> {
+        let authority = self.authority.as_deref()?;
+        let host = split_host_port(strip_userinfo(authority)).0;
+        Some(
+            host.strip_prefix('[')
+                .and_then(|host| host.strip_suffix(']'))
+                .unwrap_or(host),
+        )
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        let authority = self.authority.as_deref()?;
+        split_host_port(strip_userinfo(authority)).1
+    }
+
+    /// The path as received, still percent-encoded.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    pub fn decoded_path(&self) -> Result<String, ParseHttpError> {
+        match String::from_utf8(percent_decode(&self.path)?) {
+            std::result::Result::Ok(path) => Ok(path),
+            std::result::Result::Err(_) => Err(ParseHttpError::ParseError(
+                "Invalid utf-8 in decoded path".to_string(),
+            )),
+        }
+    }
+
+    /// Decodes the query as `application/x-www-form-urlencoded` pairs.
+    /// Repeated keys are kept in order.
+    pub fn query_pairs(&self) -> Result<Vec<(String, String)>, ParseHttpError> {
+        match &self.query {
+            Some(query) => parse_query(query),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Appends an encoded `key=value` pair to the query.
+    pub fn append_query_pair(&mut self, key: &str, value: &str) -> Result<(), ParseHttpError> {
+        if matches!(self.form, TargetForm::Authority | TargetForm::Asterisk) {
+            return Err(ParseHttpError::ParseError(format!(
+                "Request target {} cannot carry a query",
+                self
+            )));
+        }
+        let query = self.query.get_or_insert_with(String::new);
+        append_query_pair(query, key, value);
+        Ok(())
+    }
+
+    /// Path and query as they would appear in an origin-form target.
+    pub fn path_and_query(&self) -> String {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        match &self.query {
+            Some(query) => format!("{}?{}", path, query),
+            None => path.to_string(),
+        }
+    }
+}
+impl Display for RequestTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{}://", scheme)?;
+        }
+        if let Some(authority) = &self.authority {
+            f.write_str(authority)?;
+        }
+        f.write_str(&self.path)?;
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+impl PartialEq<str> for RequestTarget {
+    fn eq(&self, other: &str) -> bool {
+        let mut rest = Some(other);
+        if let Some(scheme) = &self.scheme {
+            rest = rest
+                .and_then(|rest| rest.strip_prefix(scheme.as_str()))
+                .and_then(|rest| rest.strip_prefix("://"));
+        }
+        if let Some(authority) = &self.authority {
+            rest = rest.and_then(|rest| rest.strip_prefix(authority.as_str()));
+        }
+        rest = rest.and_then(|rest| rest.strip_prefix(self.path.as_str()));
+        if let Some(query) = &self.query {
+            rest = rest
+                .and_then(|rest| rest.strip_prefix('?'))
+                .and_then(|rest| rest.strip_prefix(query.as_str()));
+        }
+        if let Some(fragment) = &self.fragment {
+            rest = rest
+                .and_then(|rest| rest.strip_prefix('#'))
+                .and_then(|rest| rest.strip_prefix(fragment.as_str()));
+        }
+        rest == Some("")
+    }
+}
+impl PartialEq<&str> for RequestTarget {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+fn split_path(value: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (value, fragment) = match value.find('#') {
+        Some(index) => (&value[..index], Some(&value[index + 1..])),
+        None => (value, None),
+    };
+    match value.find('?') {
+        Some(index) => (&value[..index], Some(&value[index + 1..]), fragment),
+        None => (value, None, fragment),
+    }
+}
+
+fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+fn validate_authority(authority: &str) -> Result<(), ParseHttpError> {
+    let host_port = strip_userinfo(authority);
+    let (host, port) = split_host_port(host_port);
+    let port_text = &host_port[host.len()..];
+    if host.is_empty() || !(port_text.is_empty() || port_text == ":" || port.is_some()) {
+        return Err(ParseHttpError::ParseError(format!(
+            "Invalid authority: {}",
+            authority
+        )));
+    }
+    Ok(())
+}
+
+fn strip_userinfo(authority: &str) -> &str {
+    match authority.rfind('@') {
+        Some(index) => &authority[index + 1..],
+        None => authority,
+    }
+}
+
+/// Splits `host[:port]`, keeping IPv6 literals in brackets intact.
+fn split_host_port(value: &str) -> (&str, Option<u16>) {
+    let host_end = if value.starts_with('[') {
+        value.find(']').map(|index| index + 1).unwrap_or(value.len())
+    } else {
+        value.rfind(':').unwrap_or(value.len())
+    };
+    let port = value[host_end..]
+        .strip_prefix(':')
+        .and_then(|port| port.parse::<u16>().ok());
+    (&value[..host_end], port)
+}
+
+/// Decodes `%XX` escapes. Malformed escapes are rejected.
+pub fn percent_decode(input: &str) -> Result<Vec<u8>, ParseHttpError> {
+    let bytes = input.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let high = bytes.get(index + 1).and_then(|b| (*b as char).to_digit(16));
+            let low = bytes.get(index + 2).and_then(|b| (*b as char).to_digit(16));
+            match (high, low) {
+                (Some(high), Some(low)) => result.push((high * 16 + low) as u8),
+                _ => {
+                    return Err(ParseHttpError::ParseError(format!(
+                        "Invalid percent-encoding in {}",
+                        input
+                    )))
+                }
+            }
+            index += 3;
+        } else {
+            result.push(bytes[index]);
+            index += 1;
+        }
+    }
+    Ok(result)
+}
+
+/// Parses `application/x-www-form-urlencoded` data such as a query string.
+pub fn parse_query(query: &str) -> Result<Vec<(String, String)>, ParseHttpError> {
+    let mut result = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = match pair.find('=') {
+            Some(index) => (&pair[..index], &pair[index + 1..]),
+            None => (pair, ""),
+        };
+        result.push((decode_query_component(key)?, decode_query_component(value)?));
+    }
+    Ok(result)
+}
+
+/// Decodes one query component, turning `+` into a space.
+pub fn decode_query_component(value: &str) -> Result<String, ParseHttpError> {
+    let decoded = percent_decode(&value.replace('+', " "))?;
+    match String::from_utf8(decoded) {
+        std::result::Result::Ok(value) => Ok(value),
+        std::result::Result::Err(_) => Err(ParseHttpError::ParseError(format!(
+            "Invalid utf-8 in query component {}",
+            value
+        ))),
+    }
+}
+
+/// Encodes one query component: spaces become `+` and everything but
+/// alphanumerics and `*-._` is percent-encoded.
+pub fn encode_query_component(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    // Writing to a String cannot fail
+    let _ = write_query_component(&mut result, value);
+    result
+}
+
+/// Writes one query component encoded as by `encode_query_component`.
+pub fn write_query_component(out: &mut impl fmt::Write, value: &str) -> fmt::Result {
+    for byte in value.bytes() {
+        match byte {
+            b' ' => out.write_char('+')?,
+            b'*' | b'-' | b'.' | b'_' => out.write_char(byte as char)?,
+            _ if byte.is_ascii_alphanumeric() => out.write_char(byte as char)?,
+            _ => write!(out, "%{:02X}", byte)?,
+        }
+    }
+    std::result::Result::Ok(())
+}
+
+pub fn append_query_pair(query: &mut String, key: &str, value: &str) {
+    if !query.is_empty() {
+        query.push('&');
+    }
+    let _ = write_query_component(query, key);
+    query.push('=');
+    let _ = write_query_component(query, value);
+}
+
+#[cfg(test)]
+mod test_uri {
+    use super::{parse_query, percent_decode, RequestTarget, TargetForm};
+
+    #[test]
+    fn request_target_forms_test() {
+        let origin = RequestTarget::parse("/where%20now?q=now&x#top").unwrap();
+        assert_eq!(origin.form(), TargetForm::Origin);
+        assert_eq!(origin.path(), "/where%20now");
+        assert_eq!(origin.decoded_path().unwrap(), "/where now");
+        assert_eq!(origin.query(), Some("q=now&x"));
+        assert_eq!(origin.fragment(), Some("top"));
+        assert_eq!(origin.to_string(), "/where%20now?q=now&x#top");
+
+        let absolute = RequestTarget::parse("http://user@[::1]:8080/pub/WWW?a").unwrap();
+        assert_eq!(absolute.form(), TargetForm::Absolute);
+        assert_eq!(absolute.scheme(), Some("http"));
+        assert_eq!(absolute.authority(), Some("user@[::1]:8080"));
+        assert_eq!(absolute.host(), Some("::1"));
+        assert_eq!(absolute.port(), Some(8080));
+        assert_eq!(absolute.path_and_query(), "/pub/WWW?a");
+        assert_eq!(absolute.to_string(), "http://user@[::1]:8080/pub/WWW?a");
+
+        let authority = RequestTarget::parse("www.example.com:443").unwrap();
+        assert_eq!(authority.form(), TargetForm::Authority);
+        assert_eq!(authority.host(), Some("www.example.com"));
+        assert_eq!(authority.port(), Some(443));
+        assert_eq!(authority.to_string(), "www.example.com:443");
+
+        let asterisk = RequestTarget::parse("*").unwrap();
+        assert_eq!(asterisk.form(), TargetForm::Asterisk);
+        assert_eq!(asterisk, "*");
+    }
+
+    #[test]
+    fn invalid_request_target_test() {
+        assert!(matches!(RequestTarget::parse("/a b"), crate::Result::Err(_)));
+        assert!(matches!(RequestTarget::parse("example.com"), crate::Result::Err(_)));
+        assert!(matches!(RequestTarget::parse("1http://x/"), crate::Result::Err(_)));
+        assert!(matches!(percent_decode("%zz"), crate::Result::Err(_)));
+    }
+
+    #[test]
+    fn query_pairs_test() {
+        let pairs = parse_query("a=1&b=x+y%21&a=2&flag&=empty&c=%E2%9C%93").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "x y!".to_string()),
+                ("a".to_string(), "2".to_string()),
+                ("flag".to_string(), "".to_string()),
+                ("".to_string(), "empty".to_string()),
+                ("c".to_string(), "\u{2713}".to_string()),
+            ]
+        );
+
+        let mut target = RequestTarget::parse("/search?q=1").unwrap();
+        target.append_query_pair("name", "a&b=c d").unwrap();
+        assert_eq!(target.to_string(), "/search?q=1&name=a%26b%3Dc+d");
+        assert_eq!(target.query_pairs().unwrap()[1].1, "a&b=c d");
+        assert!(matches!(
+            RequestTarget::parse("*").unwrap().append_query_pair("a", "b"),
+            crate::Result::Err(_)
+        ));
+    }
+}
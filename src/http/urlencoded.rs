@@ -0,0 +1,107 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+use std::str;
+
+use super::http::ParseHttpError;
+use super::media_type::MediaType;
+use super::uri::{parse_query, write_query_component};
+
+pub const URLENCODED_MEDIA_TYPE: &str = "application/x-www-form-urlencoded";
+
+/// An `application/x-www-form-urlencoded` body. Pairs keep their order and
+/// repeated names.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct UrlEncoded {
+    pub pairs: Vec<(String, String)>,
+}
+impl UrlEncoded {
+    pub fn new() -> UrlEncoded {
+        UrlEncoded { pairs: Vec::new() }
+    }
+
+    pub fn parse(data: &[u8]) -> Result<UrlEncoded, ParseHttpError> {
+        let data = match str::from_utf8(data) {
+            std::result::Result::Ok(data) => data,
+            std::result::Result::Err(_) => {
+                return Err(ParseHttpError::ParseBodyError(
+                    "Invalid utf-8 in urlencoded body".to_string(),
+                ))
+            }
+        };
+        Ok(UrlEncoded {
+            pairs: parse_query(data)?,
+        })
+    }
+
+    pub fn append(&mut self, name: &str, value: &str) -> &mut UrlEncoded {
+        self.pairs.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// The first value of the field.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.pairs
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn encode(&self) -> String {
+        self.to_string()
+    }
+}
+impl Display for UrlEncoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, value)) in self.pairs.iter().enumerate() {
+            if index > 0 {
+                f.write_str("&")?;
+            }
+            write_query_component(f, name)?;
+            f.write_str("=")?;
+            write_query_component(f, value)?;
+        }
+        std::result::Result::Ok(())
+    }
+}
+impl From<&UrlEncoded> for Result<Vec<u8>, ParseHttpError> {
+    fn from(form: &UrlEncoded) -> Self {
+        Ok(form.encode().into_bytes())
+    }
+}
+impl From<Vec<u8>> for Result<UrlEncoded, ParseHttpError> {
+    fn from(data: Vec<u8>) -> Self {
+        UrlEncoded::parse(&data)
+    }
+}
+
+/// Whether a Content-Type value names the urlencoded media type,
+/// ignoring parameters and case.
+pub fn is_urlencoded(content_type: &str) -> bool {
+    match MediaType::parse(content_type) {
+        Ok(media_type) => media_type.is("application", "x-www-form-urlencoded"),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test_urlencoded {
+    use super::UrlEncoded;
+
+    #[test]
+    fn urlencoded_round_trip_test() {
+        let form = UrlEncoded::parse(b"name=J%C3%BCrgen+M&tags=a&tags=b%2Bc&empty=").unwrap();
+        assert_eq!(form.get("name"), Some("J\u{fc}rgen M"));
+        assert_eq!(form.get_all("tags").collect::<Vec<_>>(), vec!["a", "b+c"]);
+        assert_eq!(form.get("empty"), Some(""));
+        assert_eq!(form.get("missing"), None);
+
+        assert_eq!(form.encode(), "name=J%C3%BCrgen+M&tags=a&tags=b%2Bc&empty=");
+    }
+}
@@ -0,0 +1,122 @@
+use crate::Result::{self, Err, Ok};
+use std::fmt::{self, Display};
+
+use super::header_map::HeaderMap;
+use super::http::{HeaderKey, ParseHttpError, StandardHeaders};
+
+/// The protocol version of an HTTP/1.x message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Version {
+    Http10,
+    #[default]
+    Http11,
+}
+impl Version {
+    /// Parses `HTTP/1.0` or `HTTP/1.1`. The name is case-sensitive
+    /// (RFC 9112 section 2.3); other well-formed versions are unsupported.
+    pub fn parse(value: &[u8]) -> Result<Version, ParseHttpError> {
+        match value {
+            b"HTTP/1.0" => Ok(Version::Http10),
+            b"HTTP/1.1" => Ok(Version::Http11),
+            [b'H', b'T', b'T', b'P', b'/', major, b'.', minor]
+                if major.is_ascii_digit() && minor.is_ascii_digit() =>
+            {
+                Err(ParseHttpError::ParseError(format!(
+                    "Unsupported HTTP version: {}",
+                    String::from_utf8_lossy(value)
+                )))
+            }
+            _ => Err(ParseHttpError::ParseError(format!(
+                "Invalid HTTP version: {}",
+                String::from_utf8_lossy(value)
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+
+    /// Whether connections stay open unless `Connection: close` is sent.
+    /// HTTP/1.0 connections close unless `keep-alive` is requested.
+    pub fn keeps_alive_by_default(&self) -> bool {
+        *self >= Version::Http11
+    }
+
+    /// Whether the chunked transfer coding may be used (RFC 9112 section 7).
+    pub fn supports_chunked(&self) -> bool {
+        *self >= Version::Http11
+    }
+
+    /// Whether requests must carry a Host header (RFC 9112 section 3.2).
+    pub fn requires_host(&self) -> bool {
+        *self >= Version::Http11
+    }
+
+    /// Whether a connection carrying a message with these headers stays open
+    /// afterwards, according to the Connection header and the version.
+    pub fn keep_alive(&self, headers: &HeaderMap) -> bool {
+        let mut close = false;
+        let mut keep_alive = false;
+        for value in headers.get_all(&HeaderKey::from(StandardHeaders::Connection)) {
+            for option in value.as_str().split(',') {
+                let option = option.trim();
+                close |= option.eq_ignore_ascii_case("close");
+                keep_alive |= option.eq_ignore_ascii_case("keep-alive");
+            }
+        }
+        if close {
+            return false;
+        }
+        keep_alive || self.keeps_alive_by_default()
+    }
+}
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl PartialEq<str> for Version {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+impl PartialEq<&str> for Version {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+#[cfg(test)]
+mod test_version {
+    use super::Version;
+    use crate::http::header_map::HeaderMap;
+    use crate::http::http::{HeaderKey, HeaderValue};
+    use crate::Result::Err;
+
+    #[test]
+    fn version_test() {
+        assert_eq!(Version::parse(b"HTTP/1.0").unwrap(), Version::Http10);
+        assert_eq!(Version::parse(b"HTTP/1.1").unwrap().to_string(), "HTTP/1.1");
+        assert!(matches!(Version::parse(b"HTTP/2.0"), Err(_)));
+        assert!(matches!(Version::parse(b"http/1.1"), Err(_)));
+        assert!(matches!(Version::parse(b"HTTP/1.10"), Err(_)));
+
+        let mut headers = HeaderMap::new();
+        assert!(!Version::Http10.keep_alive(&headers));
+        assert!(Version::Http11.keep_alive(&headers));
+        headers.append(
+            HeaderKey::new("Connection".to_string()).unwrap(),
+            HeaderValue::new("Keep-Alive".to_string()).unwrap(),
+        );
+        assert!(Version::Http10.keep_alive(&headers));
+        headers.append(
+            HeaderKey::new("Connection".to_string()).unwrap(),
+            HeaderValue::new("upgrade, close".to_string()).unwrap(),
+        );
+        assert!(!Version::Http11.keep_alive(&headers));
+    }
+}
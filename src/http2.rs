@@ -5,6 +5,7 @@ pub mod message;
 pub mod payload;
 pub mod payload_flags;
 
+use std::io::Write;
 use std::str::FromStr;
 
 pub use frame::*;
@@ -18,6 +19,17 @@ pub trait Len {
     fn binary_len(&self) -> usize;
 }
 
+/// Serialization that borrows the value instead of consuming it.
+pub trait Encode {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()>;
+
+    /// Appends the encoding to `buf`, which can be reused across frames.
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        // Writing to a Vec cannot fail
+        let _ = self.write_to(buf);
+    }
+}
+
 const DEFAULT_PRI: &str = "PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 pub struct Http2Pri {
     pub content: String,
@@ -60,6 +72,45 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn frame_encode_test() {
+        let payload = HeadersPayload {
+            PadLength: Some(2),
+            Priority: Some(PriorityPayload {
+                ExclusiveFlag: true,
+                StreamDependency: u31::new(3),
+                Weight: 16,
+            }),
+            HeaderBlockFragment: Hpack::from(vec![0x82, 0x86]),
+            Padding: Some(vec![0, 0]),
+        };
+        let frame = Frame {
+            length: u24::new(payload.binary_len() as u32),
+            frame_type: FrameType::Headers,
+            flags: HeadersPayloadFlag::END_HEADERS
+                | HeadersPayloadFlag::PADDED
+                | HeadersPayloadFlag::PRIORITY,
+            reserved: false,
+            stream_id: u31::new(5),
+            payload: Payload::Headers(payload),
+        };
+
+        let mut buf = Vec::new();
+        frame.encode_into(&mut buf);
+        frame.encode_into(&mut buf);
+        let length = frame.binary_len();
+        assert_eq!(buf.len(), 2 * length);
+        assert_eq!(&buf[..length], &buf[length..]);
+        assert_eq!(&buf[..9], &[0, 0, 10, 1, 0x2c, 0, 0, 0, 5]);
+        let encoded: Vec<u8> = frame.into();
+        assert_eq!(encoded, buf[..length].to_vec());
+
+        let mut out = Vec::new();
+        let decoded = Frame::try_from(&buf[..length]).ok().unwrap();
+        decoded.write_to(&mut out).unwrap();
+        assert_eq!(out, encoded);
+    }
+
     fn read_frame(buf: Vec<u8>, context: &mut HpackContext) -> usize {
         let mut frame = <Frame as From<Vec<u8>>>::from(buf);
         let len = frame.binary_len();
@@ -1,8 +1,9 @@
 use std::fmt::Display;
+use std::io::Write;
 
 use crate::{u24::u24, u31::u31};
 
-use super::{payload::Payload, FromBytesError, Len};
+use super::{payload::Payload, Encode, FromBytesError, Len};
 
 pub enum FrameParseError {
     InsufficentLength,
@@ -80,16 +81,21 @@ impl Into<u8> for FrameType {
 
 impl Into<Vec<u8>> for Frame {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend(self.length.to_bytes());
-        result.push(self.frame_type.into());
-        result.push(self.flags);
-        result.extend((self.stream_id.to_u32() | ((self.reserved as u32) << 31)).to_be_bytes());
-        result.extend(<Payload as Into<Vec<u8>>>::into(self.payload));
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
+impl Encode for Frame {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&self.length.to_bytes())?;
+        w.write_all(&[self.frame_type.clone().into(), self.flags])?;
+        w.write_all(&(self.stream_id.to_u32() | ((self.reserved as u32) << 31)).to_be_bytes())?;
+        self.payload.write_to(w)
+    }
+}
+
 impl From<u8> for FrameType {
     fn from(value: u8) -> Self {
         match value {
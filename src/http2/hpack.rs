@@ -1,5 +1,8 @@
 use std::collections::{HashMap, VecDeque};
 
+use crate::http::header_map::HeaderMap;
+use crate::http::http::{HeaderCase, HeaderKey, StandardHeaders};
+
 use super::Len;
 
 #[derive(Debug)]
@@ -134,7 +137,10 @@ impl HpackContext {
                 return Some(i + 1);
             }
         }
-        // Check dynamic table
+        self.find_dynamic_header(name, value)
+    }
+
+    fn find_dynamic_header(&self, name: &[u8], value: &[u8]) -> Option<usize> {
         for (i, (dyn_name, dyn_value)) in self.dynamic_table.iter().enumerate() {
             if dyn_name == name && dyn_value == value {
                 return Some(STATIC_TABLE.len() + i + 1);
@@ -260,6 +266,55 @@ pub fn encode_header(name: &[u8], value: &[u8], context: &mut HpackContext) -> V
     encoded
 }
 
+/// Encodes a registered field. Names with an HPACK static table entry are
+/// indexed through `StandardHeaders::hpack_index` instead of being searched
+/// for in the static table.
+pub fn encode_standard_header(
+    header: &StandardHeaders,
+    value: &[u8],
+    context: &mut HpackContext,
+) -> Vec<u8> {
+    let Some(index) = header.hpack_index() else {
+        return encode_header(header.as_str().to_ascii_lowercase().as_bytes(), value, context);
+    };
+    let (name, static_value) = STATIC_TABLE[index - 1];
+    let mut encoded = Vec::new();
+    if static_value.as_bytes() == value {
+        // Indexed Header Field
+        encoded.extend(encode_integer(index, 7));
+        encoded[0] |= 0x80;
+    } else if let Some(dynamic_index) = context.find_dynamic_header(name.as_bytes(), value) {
+        encoded.extend(encode_integer(dynamic_index, 7));
+        encoded[0] |= 0x80;
+    } else {
+        // Literal Header Field with Incremental Indexing - Indexed Name
+        encoded.extend(encode_integer(index, 6));
+        encoded[0] |= 0x40;
+        encoded.extend(encode_string(value, false).unwrap());
+        context.add_header(name.as_bytes().to_vec(), value.to_vec());
+    }
+    encoded
+}
+
+/// Encodes every field of `headers` with lowercased names, as HTTP/2
+/// requires (RFC 9113 section 8.2.1).
+pub fn encode_header_map(headers: &HeaderMap, context: &mut HpackContext) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for (key, value) in headers {
+        let value = value.as_str().as_bytes();
+        match key {
+            HeaderKey::StandardHeader(header, _) => {
+                encoded.extend(encode_standard_header(header, value, context))
+            }
+            HeaderKey::CustomHeader(_) => {
+                let name = key.to_cased_string(HeaderCase::Lowercase);
+                encoded.extend(encode_header(name.as_bytes(), value, context))
+            }
+        }
+    }
+    encoded
+}
+
 pub fn encode_headers(headers: &[(Vec<u8>, Vec<u8>)], context: &mut HpackContext) -> Vec<u8> {
     let mut encoded = Vec::new();
     for (name, value) in headers {
@@ -370,6 +425,11 @@ impl Hpack {
         &self.encoded
     }
 
+    pub fn encode_header_map(&mut self, headers: &HeaderMap, context: &mut HpackContext) -> &[u8] {
+        self.encoded = encode_header_map(headers, context);
+        &self.encoded
+    }
+
     pub fn decode(
         &mut self,
         context: &mut HpackContext,
@@ -380,6 +440,10 @@ impl Hpack {
     pub fn encoded_size(&self) -> usize {
         self.encoded.len()
     }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.encoded
+    }
 }
 
 impl From<Vec<u8>> for Hpack {
@@ -401,3 +465,51 @@ impl Len for Hpack {
         self.encoded_size()
     }
 }
+
+#[cfg(test)]
+mod test_hpack {
+    use super::{
+        encode_header, encode_header_map, encode_standard_header, HpackContext, STATIC_TABLE,
+    };
+    use crate::http::header_map::HeaderMap;
+    use crate::http::http::{HeaderKey, HeaderValue, StandardHeaders};
+    use std::str::FromStr;
+
+    #[test]
+    fn static_table_index_test() {
+        // Every regular field name in the static table is registered
+        for (i, (name, _)) in STATIC_TABLE.iter().enumerate().skip(14) {
+            let header = StandardHeaders::from_str(name).unwrap();
+            assert_eq!(header.hpack_index(), Some(i + 1), "{}", name);
+            assert_eq!(header.as_str().to_ascii_lowercase(), *name);
+        }
+        assert_eq!(StandardHeaders::Keep_Alive.hpack_index(), None);
+
+        for (header, value) in [
+            (StandardHeaders::Accept_Encoding, &b"gzip, deflate"[..]),
+            (StandardHeaders::Content_Type, &b"text/html"[..]),
+            (StandardHeaders::Content_Type, &b"text/html"[..]),
+            (StandardHeaders::Sec_WebSocket_Key, &b"abc"[..]),
+        ] {
+            let mut by_index = HpackContext::new(4096);
+            let mut by_name = HpackContext::new(4096);
+            let name = header.as_str().to_ascii_lowercase();
+            assert_eq!(
+                encode_standard_header(&header, value, &mut by_index),
+                encode_header(name.as_bytes(), value, &mut by_name)
+            );
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.append(
+            HeaderKey::from(StandardHeaders::Content_Length),
+            HeaderValue::new("5".to_string()).unwrap(),
+        );
+        headers.append(
+            HeaderKey::new("X-Trace".to_string()).unwrap(),
+            HeaderValue::new("1".to_string()).unwrap(),
+        );
+        let encoded = encode_header_map(&headers, &mut HpackContext::new(4096));
+        assert_eq!(encoded, b"\x5c\x015\x40\x07x-trace\x011".to_vec());
+    }
+}
@@ -1,3 +1,4 @@
+use std::io::Write;
 use std::result;
 
 use crate::u31::u31;
@@ -6,7 +7,7 @@ use super::{
     frame::FrameType,
     hpack::{self, Hpack},
     payload_flags::{DataPayloadFlag, HeadersPayloadFlag, PushPromisePayloadFlag},
-    Len,
+    Encode, Len,
 };
 
 #[derive(Debug)]
@@ -128,130 +129,202 @@ pub enum Payload {
 
 impl Into<Vec<u8>> for DataPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        if let Some(pad_length) = self.PadLength {
-            result.push(pad_length);
-        }
-        result.extend(self.data);
-        if let Some(padding) = self.Padding {
-            result.extend(padding);
-        }
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for PriorityPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        let c = ((self.ExclusiveFlag as u32) << 31) | self.StreamDependency.to_u32();
-        result.extend(c.to_be_bytes());
-        result.push(self.Weight);
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for HeadersPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        if let Some(pad_length) = self.PadLength {
-            result.push(pad_length);
-        }
-        if let Some(priority) = self.Priority {
-            result.extend::<Vec<u8>>(priority.into());
-        }
-
-        result.extend::<Vec<u8>>(self.HeaderBlockFragment.into());
-
-        if let Some(padding) = self.Padding {
-            result.extend(padding);
-        }
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for RstStreamPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend(self.ErrorCode.to_be_bytes());
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for SettingsPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        for (key, value) in self.settings {
-            result.extend(key.to_be_bytes());
-            result.extend(value.to_be_bytes());
-        }
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for PushPromisePayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        if let Some(pad_length) = self.PadLength {
-            result.push(pad_length);
-        }
-
-        result.extend(self.PromisedStreamId.to_bytes());
-        result.extend::<Vec<u8>>(self.HeaderBlockFragment.into());
-
-        if let Some(padding) = self.Padding {
-            result.extend(padding);
-        }
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for PingPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend(self.OpaqueData.to_be_bytes());
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for GoAwayPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend(self.LastStreamId.to_bytes());
-        result.extend(self.ErrorCode.to_be_bytes());
-        result.extend(self.AdditionalData);
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for WindowUpdatePayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend(self.WindowSizeIncrement.to_be_bytes());
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for ContinuationPayload {
     fn into(self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend::<Vec<u8>>(self.HeaderBlockFragment.into());
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
         result
     }
 }
 
 impl Into<Vec<u8>> for Payload {
     fn into(self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(self.binary_len());
+        self.encode_into(&mut result);
+        result
+    }
+}
+
+impl Encode for DataPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        if let Some(pad_length) = self.PadLength {
+            w.write_all(&[pad_length])?;
+        }
+        w.write_all(&self.data)?;
+        if let Some(padding) = &self.Padding {
+            w.write_all(padding)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for PriorityPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        let c = ((self.ExclusiveFlag as u32) << 31) | self.StreamDependency.to_u32();
+        w.write_all(&c.to_be_bytes())?;
+        w.write_all(&[self.Weight])
+    }
+}
+
+impl Encode for HeadersPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        if let Some(pad_length) = self.PadLength {
+            w.write_all(&[pad_length])?;
+        }
+        if let Some(priority) = &self.Priority {
+            priority.write_to(w)?;
+        }
+
+        w.write_all(self.HeaderBlockFragment.as_bytes())?;
+
+        if let Some(padding) = &self.Padding {
+            w.write_all(padding)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for RstStreamPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&self.ErrorCode.to_be_bytes())
+    }
+}
+
+impl Encode for SettingsPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        for (key, value) in &self.settings {
+            w.write_all(&key.to_be_bytes())?;
+            w.write_all(&value.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for PushPromisePayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        if let Some(pad_length) = self.PadLength {
+            w.write_all(&[pad_length])?;
+        }
+
+        w.write_all(&self.PromisedStreamId.to_bytes())?;
+        w.write_all(self.HeaderBlockFragment.as_bytes())?;
+
+        if let Some(padding) = &self.Padding {
+            w.write_all(padding)?;
+        }
+        Ok(())
+    }
+}
+
+impl Encode for PingPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&self.OpaqueData.to_be_bytes())
+    }
+}
+
+impl Encode for GoAwayPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&self.LastStreamId.to_bytes())?;
+        w.write_all(&self.ErrorCode.to_be_bytes())?;
+        w.write_all(&self.AdditionalData)
+    }
+}
+
+impl Encode for WindowUpdatePayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&self.WindowSizeIncrement.to_be_bytes())
+    }
+}
+
+impl Encode for ContinuationPayload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(self.HeaderBlockFragment.as_bytes())
+    }
+}
+
+impl Encode for Payload {
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
         match self {
-            Payload::Data(v) => v.into(),
-            Payload::Headers(v) => v.into(),
-            Payload::Priority(v) => v.into(),
-            Payload::RstStream(v) => v.into(),
-            Payload::Settings(v) => v.into(),
-            Payload::PushPromise(v) => v.into(),
-            Payload::Ping(v) => v.into(),
-            Payload::GoAway(v) => v.into(),
-            Payload::WindowUpdate(v) => v.into(),
-            Payload::Continuation(v) => v.into(),
+            Payload::Data(v) => v.write_to(w),
+            Payload::Headers(v) => v.write_to(w),
+            Payload::Priority(v) => v.write_to(w),
+            Payload::RstStream(v) => v.write_to(w),
+            Payload::Settings(v) => v.write_to(w),
+            Payload::PushPromise(v) => v.write_to(w),
+            Payload::Ping(v) => v.write_to(w),
+            Payload::GoAway(v) => v.write_to(w),
+            Payload::WindowUpdate(v) => v.write_to(w),
+            Payload::Continuation(v) => v.write_to(w),
         }
     }
 }
@@ -19,7 +19,7 @@ impl u31 {
     }
 
     pub fn to_bytes(self) -> [u8; 4] {
-        <Self as Into<Vec<u8>>>::into(self).try_into().unwrap()
+        self.0.to_be_bytes()
     }
 
     pub fn from_bytes(value: [u8; 4]) -> Self {